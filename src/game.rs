@@ -1,4 +1,5 @@
 use crate::input::Input;
+use crate::game_time::GameTime;
 use crate::graphics::draw::Draw;
 
 
@@ -7,10 +8,13 @@ pub trait Game
     fn process_input(&mut self, input: &mut Input) {
     }
 
-    fn update(&mut self) {
+    fn update(&mut self, _game_time: &GameTime) {
     }
 
-    fn draw(&mut self, draw: &mut Draw) 
+    fn on_resize(&mut self, _width: u32, _height: u32) {
+    }
+
+    fn draw(&mut self, draw: &mut Draw, _game_time: &GameTime) 
     {
     }
 }
\ No newline at end of file
@@ -0,0 +1,20 @@
+#[derive(Copy, Clone, Debug)]
+pub struct GameTime
+{
+    /// Length of one fixed update step in seconds.
+    pub delta_time: f32,
+    /// Simulated time in seconds, advanced by `delta_time` on every update.
+    pub total_time: f64,
+    /// Number of frames drawn so far.
+    pub frame_count: u64,
+    /// Fraction of a fixed step left in the accumulator, use it to interpolate
+    /// between the previous and current simulation state when drawing.
+    pub alpha: f32,
+}
+
+impl GameTime
+{
+    pub fn new(delta_time: f32) -> Self {
+        Self { delta_time, total_time: 0.0, frame_count: 0, alpha: 0.0 }
+    }
+}
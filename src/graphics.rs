@@ -1,3 +1,14 @@
 pub mod draw;
+pub mod batch;
+pub mod bitmap_font;
+pub mod blend_mode;
+pub mod camera;
 pub mod colour;
-pub mod texture;
\ No newline at end of file
+pub mod font;
+pub mod material;
+pub mod post_effect;
+pub mod render_target;
+pub mod sprite_options;
+pub mod text;
+pub mod texture;
+mod tessellation;
\ No newline at end of file
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+use crate::graphics::material::Material;
+use crate::shapes::rectangle::Rectangle;
+use crate::graphics::blend_mode::BlendMode;
+use crate::graphics::render_target::RenderTarget;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SortMode
+{
+    /// Draws in the order the calls were made.
+    Deferred,
+    /// Largest layer depth first, so smaller depths end up on top.
+    BackToFront,
+    /// Smallest layer depth first, so larger depths end up on top.
+    FrontToBack,
+    /// Groups draws that share a texture, shapes come before sprites.
+    Texture,
+    /// Draws whatever reaches furthest down the screen last, so it covers things above it.
+    YSort,
+}
+
+/// How `Draw::begin_with` should treat the draws until `end`.
+#[derive(Clone, Debug)]
+pub struct BatchSettings
+{
+    pub sort_mode: SortMode,
+    /// Blend mode the batch starts with, `Draw::set_blend_mode` can change it between draws.
+    pub blend_mode: BlendMode,
+    /// Draws the batch's sprites with a custom shader instead of the built in one, shapes are unaffected.
+    pub material: Option<Arc<Material>>,
+    /// Area of the target, in pixels, the batch is drawn into, `(0, 0)` of the camera is its top left.
+    pub viewport: Option<Rectangle>,
+    /// Area of the target, in pixels, outside of which nothing is drawn.
+    pub scissor: Option<Rectangle>,
+    /// Texture the batch draws into instead of the frame, viewports and scissors are then in its pixels.
+    pub render_target: Option<RenderTarget>,
+}
+
+impl BatchSettings
+{
+    pub fn new() -> Self {
+        Self { sort_mode: SortMode::Deferred, blend_mode: BlendMode::Alpha, material: None, viewport: None, scissor: None, render_target: None }
+    }
+
+    pub fn sort_mode(mut self, sort_mode: SortMode) -> Self {
+        self.sort_mode = sort_mode;
+        self
+    }
+
+    pub fn blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    pub fn material(mut self, material: Arc<Material>) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    pub fn viewport(mut self, viewport: Rectangle) -> Self {
+        self.viewport = Some(viewport);
+        self
+    }
+
+    pub fn scissor(mut self, scissor: Rectangle) -> Self {
+        self.scissor = Some(scissor);
+        self
+    }
+
+    pub fn render_target(mut self, render_target: RenderTarget) -> Self {
+        self.render_target = Some(render_target);
+        self
+    }
+}
+
+impl Default for BatchSettings
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,430 @@
+use std::fs;
+use std::sync::Arc;
+use std::path::Path;
+use std::collections::HashMap;
+use crate::math::Vector2;
+use crate::shapes::rectangle::Rectangle;
+use crate::graphics::texture::Texture;
+use crate::platform::graphics_interface::GraphicsInterface;
+
+const BINARY_HEADER: &[u8] = b"BMF";
+const BINARY_VERSION: u8 = 3;
+
+const COMMON_BLOCK: u8 = 2;
+const PAGES_BLOCK: u8 = 3;
+const CHARS_BLOCK: u8 = 4;
+const KERNING_BLOCK: u8 = 5;
+
+const BLOCK_HEADER_SIZE: usize = 5;
+/// Only the line height and base are read from the common block.
+const BINARY_COMMON_SIZE: usize = 4;
+const BINARY_CHAR_SIZE: usize = 20;
+const BINARY_KERNING_SIZE: usize = 10;
+
+/// One character of a bitmap font, all values are in pixels of the page texture.
+#[derive(Copy, Clone, Debug)]
+pub struct BitmapGlyph
+{
+    pub page: usize,
+    /// Top left of the glyph, measured from the top of the page like the `.fnt` file does.
+    pub area: Vector2<f32>,
+    pub width: f32,
+    pub height: f32,
+    /// Offset from the pen position on the top of the line to the top left of the glyph.
+    pub offset: Vector2<f32>,
+    pub advance: f32,
+}
+
+/// A bitmap font exported by BMFont, Hiero or similar tools, loaded from either the
+/// text or the binary `.fnt` format. Page images are looked up next to the `.fnt` file.
+pub struct BitmapFont
+{
+    pub line_height: f32,
+    pub base: f32,
+    pub pages: Vec<Arc<Texture>>,
+    glyphs: HashMap<char, BitmapGlyph>,
+    kernings: HashMap<(char, char), f32>,
+}
+
+impl BitmapFont
+{
+    pub fn new_from_file(graphics_interface: &GraphicsInterface, file_path: &str) -> Result<Self, String>
+    {
+        let path = Path::new(file_path);
+
+        let data = match fs::read(path)
+        {
+            Ok(data) => data,
+            Err(error) => return Err(format!("Couldn't read bitmap font {}: {}", file_path, error)),
+        };
+
+        let directory = match path.parent()
+        {
+            Some(directory) => directory,
+            None => Path::new(""),
+        };
+
+        let mut font = Self { line_height: 0.0, base: 0.0, pages: Vec::new(), glyphs: HashMap::new(), kernings: HashMap::new() };
+
+        let page_files = if data.starts_with(BINARY_HEADER) {
+            font.parse_binary(&data)?
+        }
+        else
+        {
+            match std::str::from_utf8(&data)
+            {
+                Ok(text) => font.parse_text(text)?,
+                Err(_) => return Err(format!("Bitmap font {} is neither a binary nor a text .fnt file", file_path)),
+            }
+        };
+
+        if let Some((character, glyph)) = font.glyphs.iter().find(|(_, glyph)| glyph.page >= page_files.len()) {
+            return Err(format!("Bitmap font {} uses page {} for {:?} but only has {} pages", file_path, glyph.page, character, page_files.len()));
+        }
+
+        for page_file in page_files
+        {
+            let page_path = directory.join(&page_file);
+
+            let page_path = match page_path.to_str()
+            {
+                Some(page_path) => page_path,
+                None => return Err(format!("Bitmap font page path {:?} isn't valid UTF-8", page_path)),
+            };
+
+            font.pages.push(Arc::new(Texture::try_new_from_file(graphics_interface, page_path)?));
+        }
+
+        Ok(font)
+    }
+
+    pub fn glyph(&self, character: char) -> Option<&BitmapGlyph> {
+        self.glyphs.get(&character)
+    }
+
+    pub fn kerning(&self, left: char, right: char) -> f32 {
+        self.kernings.get(&(left, right)).copied().unwrap_or(0.0)
+    }
+
+    /// Area of `glyph`'s page that holds it, ready to be passed to `Draw::sprite_with`.
+    pub fn glyph_area(&self, glyph: &BitmapGlyph) -> Rectangle {
+        self.pages[glyph.page].area_from_top(glyph.area, glyph.width, glyph.height)
+    }
+
+    fn parse_text(&mut self, text: &str) -> Result<Vec<String>, String>
+    {
+        let mut page_files: Vec<(usize, String)> = Vec::new();
+
+        for line in text.lines()
+        {
+            let (tag, values) = match line.trim().split_once(char::is_whitespace)
+            {
+                Some((tag, rest)) => (tag, parse_values(rest)),
+                None => continue,
+            };
+
+            let number = |key: &str| -> i64 {
+                match values.get(key).map(|value| value.parse::<i64>())
+                {
+                    Some(Ok(value)) => value,
+                    _ => 0,
+                }
+            };
+
+            match tag
+            {
+                "common" =>
+                {
+                    self.line_height = number("lineHeight") as f32;
+                    self.base = number("base") as f32;
+                }
+                "page" =>
+                {
+                    match values.get("file")
+                    {
+                        Some(file) => page_files.push((number("id") as usize, file.clone())),
+                        None => return Err(format!("Bitmap font page {} has no file", number("id"))),
+                    }
+                }
+                "char" =>
+                {
+                    let character = match char::from_u32(number("id") as u32)
+                    {
+                        Some(character) => character,
+                        None => continue,
+                    };
+
+                    self.glyphs.insert(character, BitmapGlyph
+                    {
+                        page: number("page") as usize,
+                        area: Vector2::new(number("x") as f32, number("y") as f32),
+                        width: number("width") as f32,
+                        height: number("height") as f32,
+                        offset: Vector2::new(number("xoffset") as f32, number("yoffset") as f32),
+                        advance: number("xadvance") as f32,
+                    });
+                }
+                "kerning" =>
+                {
+                    if let (Some(first), Some(second)) = (char::from_u32(number("first") as u32), char::from_u32(number("second") as u32)) {
+                        self.kernings.insert((first, second), number("amount") as f32);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        page_files.sort_by_key(|(id, _)| *id);
+        Ok(page_files.into_iter().map(|(_, file)| file).collect())
+    }
+
+    fn parse_binary(&mut self, data: &[u8]) -> Result<Vec<String>, String>
+    {
+        if data.len() < 4 || data[3] != BINARY_VERSION {
+            return Err(format!("Only version {} of the binary bitmap font format is supported", BINARY_VERSION));
+        }
+
+        let mut page_files = Vec::new();
+        let mut cursor = 4;
+
+        while cursor < data.len()
+        {
+            if cursor + BLOCK_HEADER_SIZE > data.len() {
+                return Err(String::from("Bitmap font ends in the middle of a block header"));
+            }
+
+            let block_type = data[cursor];
+            let block_size = read_u32(data, cursor + 1) as usize;
+            let start = cursor + BLOCK_HEADER_SIZE;
+
+            let end = match start.checked_add(block_size)
+            {
+                Some(end) if end <= data.len() => end,
+                _ => return Err(format!("Bitmap font block {} runs past the end of the file", block_type)),
+            };
+
+            let block = &data[start..end];
+
+            match block_type
+            {
+                COMMON_BLOCK =>
+                {
+                    if block.len() < BINARY_COMMON_SIZE {
+                        return Err(format!("Bitmap font common block is {} bytes, at least {} are needed", block.len(), BINARY_COMMON_SIZE));
+                    }
+
+                    self.line_height = read_u16(block, 0) as f32;
+                    self.base = read_u16(block, 2) as f32;
+                }
+                PAGES_BLOCK =>
+                {
+                    for name in block.split(|byte| *byte == 0).filter(|name| !name.is_empty()) {
+                        page_files.push(String::from_utf8_lossy(name).into_owned());
+                    }
+                }
+                CHARS_BLOCK =>
+                {
+                    if !block.len().is_multiple_of(BINARY_CHAR_SIZE) {
+                        return Err(format!("Bitmap font chars block is {} bytes, not a whole number of {} byte chars", block.len(), BINARY_CHAR_SIZE));
+                    }
+
+                    for glyph in block.chunks_exact(BINARY_CHAR_SIZE)
+                    {
+                        let character = match char::from_u32(read_u32(glyph, 0))
+                        {
+                            Some(character) => character,
+                            None => continue,
+                        };
+
+                        self.glyphs.insert(character, BitmapGlyph
+                        {
+                            page: glyph[18] as usize,
+                            area: Vector2::new(read_u16(glyph, 4) as f32, read_u16(glyph, 6) as f32),
+                            width: read_u16(glyph, 8) as f32,
+                            height: read_u16(glyph, 10) as f32,
+                            offset: Vector2::new(read_u16(glyph, 12) as i16 as f32, read_u16(glyph, 14) as i16 as f32),
+                            advance: read_u16(glyph, 16) as i16 as f32,
+                        });
+                    }
+                }
+                KERNING_BLOCK =>
+                {
+                    if !block.len().is_multiple_of(BINARY_KERNING_SIZE) {
+                        return Err(format!("Bitmap font kerning block is {} bytes, not a whole number of {} byte pairs", block.len(), BINARY_KERNING_SIZE));
+                    }
+
+                    for pair in block.chunks_exact(BINARY_KERNING_SIZE)
+                    {
+                        if let (Some(first), Some(second)) = (char::from_u32(read_u32(pair, 0)), char::from_u32(read_u32(pair, 4))) {
+                            self.kernings.insert((first, second), read_u16(pair, 8) as i16 as f32);
+                        }
+                    }
+                }
+                _ => {}
+            }
+
+            cursor = end;
+        }
+
+        Ok(page_files)
+    }
+}
+
+/// Splits `key=value` pairs, values may be quoted to hold spaces.
+fn parse_values(text: &str) -> HashMap<String, String>
+{
+    let mut values = HashMap::new();
+    let mut rest = text.trim_start();
+
+    while let Some(equals) = rest.find('=')
+    {
+        let key = rest[..equals].trim().to_string();
+        rest = &rest[equals + 1..];
+
+        let value = if let Some(quoted) = rest.strip_prefix('"')
+        {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            let value = &quoted[..end];
+            rest = &quoted[(end + 1).min(quoted.len())..];
+            value
+        }
+        else
+        {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let value = &rest[..end];
+            rest = &rest[end..];
+            value
+        };
+
+        values.insert(key, value.to_string());
+        rest = rest.trim_start();
+    }
+
+    values
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn empty_font() -> BitmapFont {
+        BitmapFont { line_height: 0.0, base: 0.0, pages: Vec::new(), glyphs: HashMap::new(), kernings: HashMap::new() }
+    }
+
+    fn block(block_type: u8, contents: &[u8]) -> Vec<u8>
+    {
+        let mut block = vec![block_type];
+        block.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+        block.extend_from_slice(contents);
+        block
+    }
+
+    /// A binary `.fnt` with one page, an `A` and a kerning pair, `chars` and `kernings` replace those blocks when given.
+    fn binary_font(chars: Option<Vec<u8>>, kernings: Option<Vec<u8>>) -> Vec<u8>
+    {
+        let mut common = Vec::new();
+        common.extend_from_slice(&12u16.to_le_bytes());
+        common.extend_from_slice(&10u16.to_le_bytes());
+        common.extend_from_slice(&[0; 11]);
+
+        let mut glyph = Vec::new();
+        glyph.extend_from_slice(&('A' as u32).to_le_bytes());
+        for value in [2u16, 3, 8, 9, 1, (-2i16) as u16, 7] {
+            glyph.extend_from_slice(&value.to_le_bytes());
+        }
+        glyph.extend_from_slice(&[0, 15]);
+
+        let mut pair = Vec::new();
+        pair.extend_from_slice(&('A' as u32).to_le_bytes());
+        pair.extend_from_slice(&('V' as u32).to_le_bytes());
+        pair.extend_from_slice(&(-3i16).to_le_bytes());
+
+        let mut data = b"BMF".to_vec();
+        data.push(BINARY_VERSION);
+        data.extend(block(COMMON_BLOCK, &common));
+        data.extend(block(PAGES_BLOCK, b"page_0.png\0"));
+        data.extend(block(CHARS_BLOCK, &chars.unwrap_or(glyph)));
+        data.extend(block(KERNING_BLOCK, &kernings.unwrap_or(pair)));
+        data
+    }
+
+    #[test]
+    fn binary_font_is_parsed()
+    {
+        let mut font = empty_font();
+        let page_files = font.parse_binary(&binary_font(None, None));
+
+        assert_eq!(page_files, Ok(vec![String::from("page_0.png")]));
+        assert_eq!(font.line_height, 12.0);
+        assert_eq!(font.base, 10.0);
+        assert_eq!(font.kerning('A', 'V'), -3.0);
+
+        let glyph = match font.glyph('A')
+        {
+            Some(glyph) => *glyph,
+            None => panic!("A wasn't parsed"),
+        };
+
+        assert_eq!(glyph.area, Vector2::new(2.0, 3.0));
+        assert_eq!((glyph.width, glyph.height), (8.0, 9.0));
+        assert_eq!(glyph.offset, Vector2::new(1.0, -2.0));
+        assert_eq!(glyph.advance, 7.0);
+        assert_eq!(glyph.page, 0);
+    }
+
+    #[test]
+    fn truncated_chars_block_is_an_error() {
+        assert!(empty_font().parse_binary(&binary_font(Some(vec![0; BINARY_CHAR_SIZE - 1]), None)).is_err());
+    }
+
+    #[test]
+    fn truncated_kerning_block_is_an_error() {
+        assert!(empty_font().parse_binary(&binary_font(None, Some(vec![0; BINARY_KERNING_SIZE + 3]))).is_err());
+    }
+
+    #[test]
+    fn short_common_block_is_an_error()
+    {
+        let mut data = b"BMF".to_vec();
+        data.push(BINARY_VERSION);
+        data.extend(block(COMMON_BLOCK, &[12, 0]));
+
+        assert!(empty_font().parse_binary(&data).is_err());
+    }
+
+    #[test]
+    fn block_running_past_the_end_is_an_error()
+    {
+        let mut data = binary_font(None, None);
+        data.truncate(data.len() - 1);
+
+        assert!(empty_font().parse_binary(&data).is_err());
+    }
+
+    #[test]
+    fn cut_off_block_header_is_an_error()
+    {
+        let mut data = binary_font(None, None);
+        data.extend_from_slice(&[COMMON_BLOCK, 0]);
+
+        assert!(empty_font().parse_binary(&data).is_err());
+    }
+
+    #[test]
+    fn other_versions_are_an_error()
+    {
+        let mut data = binary_font(None, None);
+        data[3] = 2;
+
+        assert!(empty_font().parse_binary(&data).is_err());
+    }
+}
@@ -0,0 +1,72 @@
+use wgpu::{BlendComponent, BlendFactor, BlendOperation, BlendState};
+
+/// How a draw is combined with what is already on the target.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode
+{
+    /// Regular transparency for textures with straight alpha.
+    #[default]
+    Alpha,
+    /// Transparency for textures whose colour is already multiplied by their alpha.
+    Premultiplied,
+    /// Adds the colour on top, brightening what is behind, for fire, glow and explosions.
+    Additive,
+    /// Multiplies with what is behind, darkening it, for shadows and tinting.
+    Multiply,
+    /// The inverse of multiply, lightens what is behind without blowing it out like additive.
+    Screen,
+}
+
+impl BlendMode
+{
+    pub const ALL: [BlendMode; 5] = [BlendMode::Alpha, BlendMode::Premultiplied, BlendMode::Additive, BlendMode::Multiply, BlendMode::Screen];
+
+    /// Position in `ALL`, used to pick the pipeline made for this mode.
+    pub fn index(self) -> usize
+    {
+        match self
+        {
+            BlendMode::Alpha => 0,
+            BlendMode::Premultiplied => 1,
+            BlendMode::Additive => 2,
+            BlendMode::Multiply => 3,
+            BlendMode::Screen => 4,
+        }
+    }
+
+    /// Multiply and screen only work on premultiplied colour, so their shaders premultiply before blending.
+    pub fn fragment_entry_point(self) -> &'static str
+    {
+        match self
+        {
+            BlendMode::Multiply | BlendMode::Screen => "fs_premultiply",
+            _ => "fs_main",
+        }
+    }
+
+    pub fn blend_state(self) -> BlendState
+    {
+        let premultiplied_alpha = BlendComponent { src_factor: BlendFactor::One, dst_factor: BlendFactor::OneMinusSrcAlpha, operation: BlendOperation::Add };
+
+        match self
+        {
+            BlendMode::Alpha => BlendState::ALPHA_BLENDING,
+            BlendMode::Premultiplied => BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            BlendMode::Additive => BlendState
+            {
+                color: BlendComponent { src_factor: BlendFactor::SrcAlpha, dst_factor: BlendFactor::One, operation: BlendOperation::Add },
+                alpha: BlendComponent { src_factor: BlendFactor::Zero, dst_factor: BlendFactor::One, operation: BlendOperation::Add },
+            },
+            BlendMode::Multiply => BlendState
+            {
+                color: BlendComponent { src_factor: BlendFactor::Dst, dst_factor: BlendFactor::OneMinusSrcAlpha, operation: BlendOperation::Add },
+                alpha: premultiplied_alpha,
+            },
+            BlendMode::Screen => BlendState
+            {
+                color: BlendComponent { src_factor: BlendFactor::One, dst_factor: BlendFactor::OneMinusSrc, operation: BlendOperation::Add },
+                alpha: premultiplied_alpha,
+            },
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use crate::shapes::rectangle::Rectangle;
+use crate::math::{Rad, Vector2, Vector3, Vector4, Matrix4, SquareMatrix};
+
+/// A 2D camera, pass it to `Draw::begin` in place of a hand built matrix.
+///
+/// `position` is the world point shown at `origin`, which starts at the centre of the viewport.
+/// For split screen give each camera its part of the target as `viewport`, batches begun with the
+/// camera draw into it unless their `BatchSettings::viewport` says otherwise.
+pub struct Camera2D
+{
+    pub position: Vector2<f32>,
+    /// Always positive, set through `set_zoom`.
+    zoom: f32,
+    pub rotation: f32,
+    pub origin: Vector2<f32>,
+    /// Area of the target, in pixels, the camera draws into.
+    pub viewport: Rectangle,
+    /// How quickly `follow` catches up, higher is faster and 0.0 snaps straight to the target.
+    pub follow_smoothing: f32,
+    /// Size of the area around `position`, in world units, the target can move in before `follow` moves the camera.
+    pub deadzone: Option<Vector2<f32>>,
+    /// World area the view is kept inside of.
+    pub bounds: Option<Rectangle>,
+}
+
+impl Camera2D
+{
+    pub fn new(viewport_width: f32, viewport_height: f32) -> Self {
+        Self::with_viewport(Rectangle::new(Vector2::new(0.0, 0.0), viewport_width, viewport_height))
+    }
+
+    pub fn with_viewport(viewport: Rectangle) -> Self
+    {
+        Self
+        {
+            position: Vector2::new(0.0, 0.0),
+            zoom: 1.0,
+            rotation: 0.0,
+            origin: Vector2::new(viewport.width / 2.0, viewport.height / 2.0),
+            viewport,
+            follow_smoothing: 0.0,
+            deadzone: None,
+            bounds: None,
+        }
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Scales the view, 2.0 shows everything twice as big. Panics unless `zoom` is positive and finite,
+    /// a zero zoom would collapse the whole world onto one point.
+    pub fn set_zoom(&mut self, zoom: f32)
+    {
+        assert!(zoom.is_finite() && zoom > 0.0, "camera zoom must be positive, got {}", zoom);
+        self.zoom = zoom;
+    }
+
+    pub fn matrix(&self) -> Matrix4<f32>
+    {
+        Matrix4::from_translation(Vector3::new(self.origin.x, self.origin.y, 0.0)) *
+        Matrix4::from_angle_z(Rad(-self.rotation)) *
+        Matrix4::from_nonuniform_scale(self.zoom, self.zoom, 1.0) *
+        Matrix4::from_translation(Vector3::new(-self.position.x, -self.position.y, 0.0))
+    }
+
+    /// Takes a position in screen pixels, like `Mouse::position`, to the world point under it.
+    pub fn screen_to_world(&self, position: Vector2<f32>) -> Vector2<f32>
+    {
+        let local_position = position - self.viewport.position;
+
+        // The zoom is never zero, so the camera matrix can always be inverted.
+        let world_position = match self.matrix().invert()
+        {
+            Some(inverse) => inverse * Vector4::new(local_position.x, local_position.y, 0.0, 1.0),
+            None => return self.position,
+        };
+
+        Vector2::new(world_position.x, world_position.y)
+    }
+
+    /// Takes a world point to where it is drawn on screen, in pixels.
+    pub fn world_to_screen(&self, position: Vector2<f32>) -> Vector2<f32>
+    {
+        let local_position = self.matrix() * Vector4::new(position.x, position.y, 0.0, 1.0);
+        Vector2::new(local_position.x, local_position.y) + self.viewport.position
+    }
+
+    /// Whether a screen position, like `Mouse::position`, is inside this camera's viewport.
+    pub fn contains_screen_position(&self, position: Vector2<f32>) -> bool {
+        position.x >= self.viewport.left() && position.x < self.viewport.right() && position.y >= self.viewport.top() && position.y < self.viewport.bottom()
+    }
+
+    /// Moves towards `target`, staying put while it is inside the deadzone, then applies the bounds.
+    pub fn follow(&mut self, target: Vector2<f32>, delta_time: f32)
+    {
+        let mut desired = target;
+
+        if let Some(deadzone) = self.deadzone
+        {
+            desired.x = self.position.x + outside_deadzone(target.x - self.position.x, deadzone.x / 2.0);
+            desired.y = self.position.y + outside_deadzone(target.y - self.position.y, deadzone.y / 2.0);
+        }
+
+        if self.follow_smoothing > 0.0 {
+            self.position += (desired - self.position) * (1.0 - (-self.follow_smoothing * delta_time).exp());
+        }
+        else {
+            self.position = desired;
+        }
+
+        self.clamp_to_bounds();
+    }
+
+    /// Moves the camera so the view stays inside `bounds`, centring on any side the view is larger than.
+    pub fn clamp_to_bounds(&mut self)
+    {
+        let bounds = match &self.bounds
+        {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        let before = self.origin / self.zoom;
+        let after = (Vector2::new(self.viewport.width, self.viewport.height) - self.origin) / self.zoom;
+
+        self.position.x = clamp_axis(self.position.x, bounds.left() + before.x, bounds.right() - after.x);
+        self.position.y = clamp_axis(self.position.y, bounds.top() + before.y, bounds.bottom() - after.y);
+    }
+
+    /// The world area the camera shows, ignoring rotation.
+    pub fn visible_area(&self) -> Rectangle
+    {
+        let top_left = self.position - self.origin / self.zoom;
+        Rectangle::new(top_left, self.viewport.width / self.zoom, self.viewport.height / self.zoom)
+    }
+}
+
+/// How far `offset` reaches past a deadzone `half_size` either side of zero.
+fn outside_deadzone(offset: f32, half_size: f32) -> f32
+{
+    if offset > half_size {
+        offset - half_size
+    }
+    else if offset < -half_size {
+        offset + half_size
+    }
+    else {
+        0.0
+    }
+}
+
+fn clamp_axis(value: f32, minimum: f32, maximum: f32) -> f32
+{
+    if minimum > maximum {
+        return (minimum + maximum) / 2.0;
+    }
+
+    value.clamp(minimum, maximum)
+}
+
+/// What `Draw::begin` takes as a camera, a `&Camera2D` or a camera matrix.
+pub trait BatchCamera
+{
+    fn camera_matrix(&self) -> Matrix4<f32>;
+
+    /// Viewport a batch draws into when its `BatchSettings` don't give one, `None` is the whole target.
+    fn camera_viewport(&self) -> Option<Rectangle> {
+        None
+    }
+}
+
+impl BatchCamera for Matrix4<f32>
+{
+    fn camera_matrix(&self) -> Matrix4<f32> {
+        *self
+    }
+}
+
+impl BatchCamera for &Camera2D
+{
+    fn camera_matrix(&self) -> Matrix4<f32> {
+        self.matrix()
+    }
+
+    fn camera_viewport(&self) -> Option<Rectangle> {
+        Some(self.viewport)
+    }
+}
+
+impl From<&Camera2D> for Matrix4<f32>
+{
+    fn from(camera: &Camera2D) -> Self {
+        camera.matrix()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn assert_close(actual: Vector2<f32>, expected: Vector2<f32>) {
+        assert!((actual.x - expected.x).abs() < 1e-3 && (actual.y - expected.y).abs() < 1e-3, "{:?} isn't {:?}", actual, expected);
+    }
+
+    fn split_screen_camera() -> Camera2D
+    {
+        let mut camera = Camera2D::with_viewport(Rectangle::new(Vector2::new(320.0, 0.0), 320.0, 240.0));
+        camera.position = Vector2::new(100.0, -50.0);
+        camera.rotation = 0.3;
+        camera.set_zoom(2.5);
+        camera
+    }
+
+    #[test]
+    fn screen_and_world_round_trip()
+    {
+        let camera = split_screen_camera();
+
+        for point in [Vector2::new(0.0, 0.0), Vector2::new(-40.0, 75.5), Vector2::new(1000.0, 3.0)]
+        {
+            assert_close(camera.screen_to_world(camera.world_to_screen(point)), point);
+            assert_close(camera.world_to_screen(camera.screen_to_world(point)), point);
+        }
+    }
+
+    #[test]
+    fn position_is_drawn_at_the_origin_of_the_viewport()
+    {
+        let camera = split_screen_camera();
+
+        assert_close(camera.world_to_screen(camera.position), Vector2::new(320.0 + 160.0, 120.0));
+        assert_close(camera.screen_to_world(Vector2::new(320.0 + 160.0, 120.0)), camera.position);
+    }
+
+    #[test]
+    fn zoom_scales_distances_from_the_position()
+    {
+        let mut camera = Camera2D::new(200.0, 100.0);
+        camera.set_zoom(2.0);
+
+        assert_close(camera.world_to_screen(Vector2::new(10.0, 5.0)), Vector2::new(120.0, 60.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_zoom_is_rejected() {
+        Camera2D::new(200.0, 100.0).set_zoom(0.0);
+    }
+
+    #[test]
+    fn target_inside_the_deadzone_doesnt_move_the_camera()
+    {
+        let mut camera = Camera2D::new(200.0, 100.0);
+        camera.deadzone = Some(Vector2::new(20.0, 10.0));
+
+        camera.follow(Vector2::new(9.0, -4.0), 1.0 / 60.0);
+        assert_close(camera.position, Vector2::new(0.0, 0.0));
+
+        camera.follow(Vector2::new(15.0, -8.0), 1.0 / 60.0);
+        assert_close(camera.position, Vector2::new(5.0, -3.0));
+    }
+
+    #[test]
+    fn smoothing_moves_part_of_the_way()
+    {
+        let mut camera = Camera2D::new(200.0, 100.0);
+        camera.follow_smoothing = 5.0;
+
+        camera.follow(Vector2::new(100.0, 0.0), 0.1);
+
+        assert!(camera.position.x > 0.0 && camera.position.x < 100.0);
+    }
+
+    #[test]
+    fn view_is_kept_inside_the_bounds()
+    {
+        let mut camera = Camera2D::new(200.0, 100.0);
+        camera.bounds = Some(Rectangle::new(Vector2::new(0.0, 0.0), 1000.0, 500.0));
+
+        camera.follow(Vector2::new(-50.0, 480.0), 1.0 / 60.0);
+        assert_close(camera.position, Vector2::new(100.0, 450.0));
+
+        camera.set_zoom(2.0);
+        camera.follow(Vector2::new(-50.0, 480.0), 1.0 / 60.0);
+        assert_close(camera.position, Vector2::new(50.0, 475.0));
+    }
+
+    #[test]
+    fn bounds_smaller_than_the_view_centre_it()
+    {
+        let mut camera = Camera2D::new(200.0, 100.0);
+        camera.bounds = Some(Rectangle::new(Vector2::new(0.0, 0.0), 100.0, 50.0));
+
+        camera.follow(Vector2::new(500.0, 500.0), 1.0 / 60.0);
+
+        assert_close(camera.position, Vector2::new(50.0, 25.0));
+    }
+}
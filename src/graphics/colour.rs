@@ -1,5 +1,6 @@
 const COLOR_RANGE: f64 = 255.0;
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Colour
 {
     pub red:   u16,
@@ -8,6 +9,7 @@ pub struct Colour
     pub alpha: u16
 }
 
+#[derive(Copy, Clone, Debug)]
 pub struct Color
 {
     pub r: f64,
@@ -31,6 +33,33 @@ impl Colour
 
         Color { r, g, b, a }
     }
+
+    /// Looks up one of the named colours below, case insensitive, or parses `#rrggbb` or `#rrggbbaa`.
+    pub fn from_name(name: &str) -> Option<Colour>
+    {
+        if let Some(hex) = name.strip_prefix('#')
+        {
+            if !hex.is_ascii() || (hex.len() != 6 && hex.len() != 8) {
+                return None;
+            }
+
+            let channel = |index: usize| u16::from_str_radix(&hex[index..index + 2], 16).ok();
+            let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+
+            return Some(Colour::new(channel(0)?, channel(2)?, channel(4)?, alpha));
+        }
+
+        match name.to_ascii_lowercase().as_str()
+        {
+            "white" => Some(Colour::WHITE),
+            "red" => Some(Colour::RED),
+            "green" => Some(Colour::GREEN),
+            "blue" => Some(Colour::BLUE),
+            "black" => Some(Colour::BLACK),
+            "cornflowerblue" => Some(Colour::CORNFLOWERBLUE),
+            _ => None,
+        }
+    }
 }
 
 impl Colour
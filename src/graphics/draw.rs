@@ -1,25 +1,54 @@
+use log::warn;
 use image::RgbaImage;
-use std::{sync::Arc, collections::HashMap, f32::consts::PI};
+use std::{sync::{Arc, Mutex}, collections::HashMap, ops::Range, path::Path};
 use crate::shapes::circle::Circle;
+use crate::platform::virtual_resolution::ScalingMode;
+use crate::graphics::tessellation::{self, circle_points};
+use crate::graphics::batch::{BatchSettings, SortMode};
+use crate::graphics::camera::BatchCamera;
+use crate::graphics::blend_mode::BlendMode;
+use crate::graphics::post_effect::PostEffect;
+use crate::graphics::font::Font;
+use crate::graphics::bitmap_font::BitmapFont;
+use crate::graphics::text::TextFont;
+use crate::graphics::text::layout::{TextLayout, TextSettings};
+use crate::graphics::sprite_options::SpriteOptions;
 use crate::{graphics::colour::Colour, shapes::rectangle::Rectangle};
 use crate::{math::Rad, math::Vector2, math::Vector3, math::Vector4, math::Matrix4, math::SquareMatrix};
-use crate::{platform::graphics_interface::{SpriteVertex, ShapeVertex, GraphicsInterface}, graphics::texture::Texture};
+use crate::{platform::graphics_interface::{SpriteVertex, ShapeVertex, BatchCommand, BatchData, GraphicsInterface}, graphics::texture::Texture};
+
+struct DrawItem
+{
+    kind: DrawItemKind,
+    layer_depth: f32,
+    bottom: f32,
+    blend_mode: BlendMode,
+}
+
+enum DrawItemKind
+{
+    Sprite { texture: Arc<Texture>, vertex_start: u32 },
+    Shape { indices: Range<usize> },
+}
 
 pub struct Draw
 {
-    sprite_draw_count: u16,
-    rectangle_draw_count: u16,
-    circle_draw_count: u16,
+    circle_segments: usize,
+    layer_depth: f32,
+    blend_mode: BlendMode,
     batch_began: bool,
-    texture_index: u32,
-    sprite_indices: Vec<u16>,
+    batch_settings: BatchSettings,
+    items: Vec<DrawItem>,
+    sprite_indices: Vec<u32>,
     sprite_vertices: Vec<SpriteVertex>,
-    rectangle_indices: Vec<u16>,
-    rectangle_vertices: Vec<ShapeVertex>,
-    circle_indices: Vec<u16>,
-    circle_vertices: Vec<ShapeVertex>,
+    shape_indices: Vec<u32>,
+    shape_vertices: Vec<ShapeVertex>,
+    ordered_shape_indices: Vec<u32>,
+    commands: Vec<BatchCommand>,
     dummy_texture: Arc<Texture>,
     camera_matrix: Matrix4<f32>,
+    projection_matrix: Matrix4<f32>,
+    batch_texture_slots: usize,
     texture_vec: Vec<Arc<Texture>>,
     texture_hashmap: HashMap<u64, u32>,
     pub graphics_interface: GraphicsInterface,
@@ -30,37 +59,40 @@ impl Draw
     pub fn new(graphics_interface: GraphicsInterface) -> Self 
     {
 
-        let sprite_indices: Vec<u16> = Vec::new();
+        let sprite_indices: Vec<u32> = Vec::new();
         let sprite_vertices: Vec<SpriteVertex> = Vec::new();
-        let rectangle_indices: Vec<u16> = Vec::new();
-        let rectangle_vertices: Vec<ShapeVertex> = Vec::new();
-        let circle_indices: Vec<u16> = Vec::new();
-        let circle_vertices: Vec<ShapeVertex> = Vec::new();
+        let shape_indices: Vec<u32> = Vec::new();
+        let shape_vertices: Vec<ShapeVertex> = Vec::new();
+        let ordered_shape_indices: Vec<u32> = Vec::new();
         let camera_matrix = Matrix4::identity();
         let texture_hashmap: HashMap<u64, u32> = HashMap::new();
-        let texture_vec: Vec<Arc<Texture>> = Vec::with_capacity(16);
+        let batch_texture_slots = graphics_interface.max_batch_textures() as usize;
+        let texture_vec: Vec<Arc<Texture>> = Vec::with_capacity(batch_texture_slots);
 
         let image_buffer = RgbaImage::new(1, 1);
         let dummy_texture =Arc::new(Texture::new_from_buffer(&graphics_interface, image_buffer, Vector2 { x: 1, y: 1 }));
 
         Self 
         { 
-            sprite_draw_count: 0,
-            rectangle_draw_count: 0, 
-            circle_draw_count: 0,
+            circle_segments: 32,
+            layer_depth: 0.0,
+            blend_mode: BlendMode::Alpha,
+            items: Vec::new(),
             sprite_indices, 
             batch_began: false,
+            batch_settings: BatchSettings::new(),
             dummy_texture, 
-            texture_index: 0, 
+            batch_texture_slots,
             texture_vec, 
             texture_hashmap, 
             graphics_interface, 
             sprite_vertices, 
             camera_matrix, 
-            rectangle_indices,
-            rectangle_vertices,
-            circle_indices,
-            circle_vertices
+            projection_matrix: Matrix4::identity(),
+            shape_indices,
+            shape_vertices,
+            ordered_shape_indices,
+            commands: Vec::new(),
         }
     }
 
@@ -69,168 +101,460 @@ impl Draw
         self.graphics_interface.clear(color.r, color.g, color.b, color.a)
     }
 
-    pub fn begin(&mut self, camera_matrix: Matrix4<f32>) {
+    pub fn set_virtual_resolution(&mut self, width: u32, height: u32, scaling_mode: ScalingMode) {
+        self.graphics_interface.set_virtual_resolution(width, height, scaling_mode);
+    }
+
+    pub fn clear_virtual_resolution(&mut self) {
+        self.graphics_interface.clear_virtual_resolution();
+    }
+
+    /// Runs `effect` over every frame after the ones added before it, keep a clone of the `Arc`
+    /// to change its settings later. Takes effect from the next `begin_frame`.
+    pub fn add_post_effect(&mut self, effect: Arc<Mutex<dyn PostEffect>>) {
+        self.graphics_interface.add_post_effect(effect);
+    }
+
+    pub fn clear_post_effects(&mut self) {
+        self.graphics_interface.clear_post_effects();
+    }
+
+    /// Starts a frame, every `begin`/`end` batch until `present` draws on top of the previous one.
+    pub fn begin_frame(&mut self) {
+        self.graphics_interface.begin_frame();
+    }
+
+    pub fn present(&mut self) 
+    {
+        if self.batch_began {
+            panic!("You can't call present before calling end");
+        }
+
+        self.graphics_interface.present_frame();
+    }
+
+    /// Saves the next presented frame as a PNG in `directory`, named after the time it was taken and a running count.
+    pub fn take_screenshot(&mut self, directory: impl AsRef<Path>) {
+        self.graphics_interface.take_screenshot(directory);
+    }
+
+    /// The last presented frame, only available when drawing with `GraphicsInterface::new_headless`.
+    pub fn read_frame(&self) -> Option<RgbaImage> {
+        self.graphics_interface.read_frame()
+    }
+
+    /// Starts a batch seen through `camera`, either a `&Camera2D` or a camera matrix.
+    /// A `&Camera2D` draws into its own viewport.
+    pub fn begin(&mut self, camera: impl BatchCamera) {
+        self.begin_with(camera, BatchSettings::new());
+    }
+
+    /// Like `begin`, a viewport in `batch_settings` is used over the camera's.
+    pub fn begin_with(&mut self, camera: impl BatchCamera, mut batch_settings: BatchSettings) {
 
         if self.batch_began {
             panic!("You can't call begin twice in a row");
         }
 
+        if batch_settings.viewport.is_none() {
+            batch_settings.viewport = camera.camera_viewport();
+        }
+
         self.batch_began = true;
-        self.camera_matrix = camera_matrix;
+        self.camera_matrix = camera.camera_matrix();
+        self.projection_matrix = match (&batch_settings.render_target, &batch_settings.viewport)
+        {
+            (Some(render_target), None) => render_target.projection_matrix(),
+            (_, viewport) => self.graphics_interface.projection_matrix(viewport.as_ref()),
+        };
+        self.blend_mode = batch_settings.blend_mode;
+        self.batch_settings = batch_settings;
+    }
+
+    /// Layer depth given to the following draws, used by the `BackToFront` and `FrontToBack` sort modes.
+    /// Kept across batches until changed, 0.0 by default. `SpriteOptions::layer_depth` overrides it for one sprite
+    /// and `with_layer_depth` for a few calls.
+    pub fn set_layer_depth(&mut self, layer_depth: f32) {
+        self.layer_depth = layer_depth;
     }
 
-    pub fn sprite(&mut self, texture: Arc<Texture>, position: Vector2<f32>, draw_area: &Rectangle, size: Vector2<f32>, rotation: f32,  colour: Colour)
+    pub fn layer_depth(&self) -> f32 {
+        self.layer_depth
+    }
+
+    /// Runs `draw_calls` at `layer_depth` and restores the previous depth afterwards, giving shapes and
+    /// text a depth for just those calls: `draw.with_layer_depth(2.0, |draw| draw.fill_circle(&circle, colour))`
+    pub fn with_layer_depth(&mut self, layer_depth: f32, draw_calls: impl FnOnce(&mut Self))
+    {
+        let previous_layer_depth = self.layer_depth;
+
+        self.layer_depth = layer_depth;
+        draw_calls(self);
+        self.layer_depth = previous_layer_depth;
+    }
+
+    /// Blend mode given to the following draws until `end`, each batch starts with the one in its `BatchSettings`.
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    pub fn sprite(&mut self, texture: Arc<Texture>, position: Vector2<f32>, draw_area: &Rectangle, size: Vector2<f32>, rotation: f32,  colour: Colour) {
+        self.sprite_with(texture, position, draw_area, size, &SpriteOptions::new().rotation(rotation), colour);
+    }
+
+    pub fn sprite_with(&mut self, texture: Arc<Texture>, position: Vector2<f32>, draw_area: &Rectangle, size: Vector2<f32>, options: &SpriteOptions, colour: Colour)
     {
         if !self.batch_began {
-            panic!("You can't call begin twice in a row");
+            panic!("You can't draw before calling begin");
         }
 
         let color = colour.converted_to_color();
 
-        let origin_x = size.x as f32 / 2.0;
-        let origin_y = size.y as f32 / 2.0;
+        let origin = match options.origin
+        {
+            Some(origin) => origin,
+            None => Vector2::new(size.x / 2.0, size.y / 2.0),
+        };
+
+        let left = -origin.x;
+        let top = -origin.y;
+        let right = size.x - origin.x;
+        let bottom = size.y - origin.y;
 
         let mut model_matrix = Matrix4::from_translation(Vector3 { x: position.x, y: position.y,  z: 0.0 });
-        model_matrix = model_matrix * Matrix4::from_angle_z(Rad(rotation));
+        model_matrix = model_matrix * Matrix4::from_angle_z(Rad(options.rotation));
+        model_matrix = model_matrix * Matrix4::from_nonuniform_scale(options.scale.x, options.scale.y, 1.0);
+
+        let final_matrix = self.projection_matrix * self.camera_matrix;
+
+        let corners = [
+            model_matrix * Vector4 { x: left,  y: top,     z: 0.0, w: 1.0 },
+            model_matrix * Vector4 { x: left,  y: bottom,  z: 0.0, w: 1.0 },
+            model_matrix * Vector4 { x: right, y: bottom,  z: 0.0, w: 1.0 },
+            model_matrix * Vector4 { x: right, y: top,     z: 0.0, w: 1.0 },
+        ];
+
+        let lowest = corners.iter().fold(f32::MIN, |lowest, corner| lowest.max(corner.y));
+
+        let vertex_position_1 =  final_matrix * corners[0];
+        let vertex_position_2 =  final_matrix * corners[1];
+        let vertex_position_3 =  final_matrix * corners[2];
+        let vertex_position_4 =  final_matrix * corners[3];
+
+        let mut left_tex_coord = draw_area.left() / (texture.width as f32);
+        let mut right_tex_coord = draw_area.right() / (texture.width as f32);
+        let mut top_tex_coord = 1.0 - draw_area.top() / (texture.height as f32);
+        let mut bottom_tex_coord = 1.0 - draw_area.bottom() / (texture.height as f32);
 
-        let final_matrix = self.graphics_interface.world_matrix * self.camera_matrix  * model_matrix;
+        if options.flip_x {
+            std::mem::swap(&mut left_tex_coord, &mut right_tex_coord);
+        }
+
+        if options.flip_y {
+            std::mem::swap(&mut top_tex_coord, &mut bottom_tex_coord);
+        }
 
-        let vertex_position_1 =  final_matrix * Vector4 { x: -origin_x, y:  -origin_y,  z: 0.0, w: 1.0 };
-        let vertex_position_2 =  final_matrix * Vector4 { x: -origin_x, y:   origin_y,  z: 0.0, w: 1.0 };
-        let vertex_position_3 =  final_matrix * Vector4 { x:  origin_x, y:   origin_y,  z: 0.0, w: 1.0 };
-        let vertex_position_4 =  final_matrix * Vector4 { x:  origin_x, y:  -origin_y,  z: 0.0, w: 1.0 };
+        let vertex_1 = SpriteVertex { index: 0, position: [ vertex_position_1.x, vertex_position_1.y], tex_coords: [left_tex_coord,    bottom_tex_coord], color: [color.r as f32, color.g as f32, color.b as f32, color.a as f32] }; // bottom left
+        let vertex_2 = SpriteVertex { index: 0, position: [ vertex_position_2.x, vertex_position_2.y], tex_coords: [left_tex_coord,       top_tex_coord], color: [color.r as f32, color.g as f32, color.b as f32, color.a as f32] }; // top left
+        let vertex_3 = SpriteVertex { index: 0, position: [ vertex_position_3.x, vertex_position_3.y], tex_coords: [right_tex_coord,      top_tex_coord], color: [color.r as f32, color.g as f32, color.b as f32, color.a as f32] }; // top right
+        let vertex_4 = SpriteVertex { index: 0, position: [ vertex_position_4.x, vertex_position_4.y], tex_coords: [right_tex_coord,   bottom_tex_coord], color: [color.r as f32, color.g as f32, color.b as f32, color.a as f32] }; // bottom right        
+
+        let vertex_start = self.sprite_vertices.len() as u32;
+
+        self.sprite_vertices.push(vertex_1);
+        self.sprite_vertices.push(vertex_2);
+        self.sprite_vertices.push(vertex_3);
+        self.sprite_vertices.push(vertex_4);
 
-        let left_tex_coord = draw_area.left() / (texture.width as f32);
-        let right_tex_coord = draw_area.right() / (texture.width as f32);
-        let top_tex_coord = 1.0 - draw_area.top() / (texture.height as f32);
-        let bottom_tex_coord = 1.0 - draw_area.bottom() / (texture.height as f32);
+        let layer_depth = options.layer_depth.unwrap_or(self.layer_depth);
+        self.items.push(DrawItem { kind: DrawItemKind::Sprite { texture, vertex_start }, layer_depth, bottom: lowest, blend_mode: self.blend_mode });
+    }
 
-        let mut vertex_1 = SpriteVertex { index: self.texture_index, position: [ vertex_position_1.x, vertex_position_1.y], tex_coords: [left_tex_coord,    bottom_tex_coord], color: [color.r as f32, color.g as f32, color.b as f32, color.a as f32] }; // bottom left
-        let mut vertex_2 = SpriteVertex { index: self.texture_index, position: [ vertex_position_2.x, vertex_position_2.y], tex_coords: [left_tex_coord,       top_tex_coord], color: [color.r as f32, color.g as f32, color.b as f32, color.a as f32] }; // top left
-        let mut vertex_3 = SpriteVertex { index: self.texture_index, position: [ vertex_position_3.x, vertex_position_3.y], tex_coords: [right_tex_coord,      top_tex_coord], color: [color.r as f32, color.g as f32, color.b as f32, color.a as f32] }; // top right
-        let mut vertex_4 = SpriteVertex { index: self.texture_index, position: [ vertex_position_4.x, vertex_position_4.y], tex_coords: [right_tex_coord,   bottom_tex_coord], color: [color.r as f32, color.g as f32, color.b as f32, color.a as f32] }; // bottom right        
+    /// Draws `text` with its top left corner at `position`, `size` is the font size in pixels.
+    /// Each `\n` starts a new line.
+    pub fn text(&mut self, font: &mut Font, text: &str, position: Vector2<f32>, size: f32, colour: Colour)
+    {
+        let ascent = font.ascent(size);
+        let line_height = font.line_height(size);
+        let options = SpriteOptions::new().origin(Vector2::new(0.0, 0.0));
 
-        match self.texture_hashmap.get(&texture.id)
+        for (line_index, line) in text.lines().enumerate()
         {
-            Some(index) => 
+            let baseline = position.y + ascent + line_index as f32 * line_height;
+            let mut pen_x = position.x;
+            let mut previous: Option<char> = None;
+
+            for character in line.chars()
             {
-               let index_value = *index;
-                
-                if index_value > 15 {
-                    self.end();
+                if let Some(previous) = previous {
+                    pen_x += font.kerning(previous, character, size);
                 }
 
-               vertex_1.index = index_value;
-               vertex_2.index = index_value;
-               vertex_3.index = index_value;
-               vertex_4.index = index_value;
+                let glyph = font.glyph(&self.graphics_interface, character, size);
+
+                if glyph.width > 0 && glyph.height > 0
+                {
+                    let glyph_position = Vector2::new((pen_x + glyph.offset.x).round(), (baseline + glyph.offset.y).round());
+                    let glyph_size = Vector2::new(glyph.width as f32, glyph.height as f32);
+                    let area = font.glyph_area(&glyph);
+
+                    self.sprite_with(font.atlas(), glyph_position, &area, glyph_size, &options, colour);
+                }
+
+                pen_x += glyph.advance;
+                previous = Some(character);
             }
-            None =>
-            { 
-                self.texture_hashmap.insert(texture.id, self.texture_index);
-                self.texture_vec.push(texture);
-                self.texture_index += 1;
+        }
+    }
+
+    /// Draws `text` with a bitmap font, its top left corner at `position` and every glyph
+    /// multiplied by `scale`, whole numbers keep pixel art crisp.
+    pub fn bitmap_text(&mut self, font: &BitmapFont, text: &str, position: Vector2<f32>, scale: f32, colour: Colour)
+    {
+        let options = SpriteOptions::new().origin(Vector2::new(0.0, 0.0)).scale(Vector2::new(scale, scale));
+
+        for (line_index, line) in text.lines().enumerate()
+        {
+            let line_top = position.y + line_index as f32 * font.line_height * scale;
+            let mut pen_x = position.x;
+            let mut previous: Option<char> = None;
+
+            for character in line.chars()
+            {
+                let glyph = match font.glyph(character)
+                {
+                    Some(glyph) => *glyph,
+                    None => continue,
+                };
+
+                if let Some(previous) = previous {
+                    pen_x += font.kerning(previous, character) * scale;
+                }
+
+                if glyph.width > 0.0 && glyph.height > 0.0
+                {
+                    let glyph_position = Vector2::new(pen_x + glyph.offset.x * scale, line_top + glyph.offset.y * scale);
+                    let page = font.pages[glyph.page].clone();
+
+                    self.sprite_with(page, glyph_position, &font.glyph_area(&glyph), Vector2::new(glyph.width, glyph.height), &options, colour);
+                }
+
+                pen_x += glyph.advance * scale;
+                previous = Some(character);
             }
         }
+    }
 
-        self.sprite_vertices.push(vertex_1);
-        self.sprite_vertices.push(vertex_2);
-        self.sprite_vertices.push(vertex_3);
-        self.sprite_vertices.push(vertex_4);
+    /// Draws a layout made with `TextLayout::new`, `colour` is used wherever the markup doesn't set one.
+    pub fn text_layout(&mut self, font: &mut impl TextFont, layout: &TextLayout, position: Vector2<f32>, settings: &TextSettings, colour: Colour)
+    {
+        let options = SpriteOptions::new().origin(Vector2::new(0.0, 0.0));
+        let visible_characters = settings.visible_characters.unwrap_or(usize::MAX);
+
+        for glyph in layout.glyphs.iter().take_while(|glyph| glyph.index < visible_characters)
+        {
+            let quad = match font.glyph_quad(&self.graphics_interface, glyph.character, layout.font_size)
+            {
+                Some(quad) => quad,
+                None => continue,
+            };
+
+            let texture = match quad.texture
+            {
+                Some(texture) => texture,
+                None => continue,
+            };
+
+            let mut glyph_position = position + glyph.position + quad.offset;
 
-        let index_offset = 4 * self.sprite_draw_count;
+            if glyph.wave {
+                glyph_position.y += settings.wave_offset(glyph.index);
+            }
 
-        self.sprite_indices.push(0 + index_offset);
-        self.sprite_indices.push(1 + index_offset);
-        self.sprite_indices.push(3 + index_offset);
-        self.sprite_indices.push(1 + index_offset);
-        self.sprite_indices.push(2 + index_offset);
-        self.sprite_indices.push(3 + index_offset);
+            self.sprite_with(texture, glyph_position, &quad.area, quad.size, &options, glyph.colour.unwrap_or(colour));
+        }
+    }
 
-        self.sprite_draw_count += 1;
+    /// Lays out and draws `text` wrapped to the width of `bounds`, markup is described in `text::markup::parse`.
+    pub fn text_box(&mut self, font: &mut impl TextFont, text: &str, bounds: &Rectangle, settings: &TextSettings, colour: Colour)
+    {
+        let settings = settings.wrap_width(bounds.width);
+        let layout = TextLayout::new(font, &self.graphics_interface, text, &settings);
 
+        self.text_layout(font, &layout, bounds.position, &settings, colour);
     }
 
-    pub fn rectangle(&mut self, rectangle: &Rectangle, colour: Colour)
+    /// Outline of `rectangle`, `thickness` pixels wide and drawn inside its bounds.
+    pub fn rectangle(&mut self, rectangle: &Rectangle, thickness: f32, colour: Colour)
     {
         if !self.batch_began {
-            panic!("You can't call begin twice in a row");
+            panic!("You can't draw before calling begin");
         }
 
-        let color = colour.converted_to_color();
+        let thickness = thickness.min(rectangle.width / 2.0).min(rectangle.height / 2.0);
 
-        let origin_x = rectangle.width as f32 / 2.0;
-        let origin_y = rectangle.height as f32 / 2.0;
+        let outer = [
+            Vector2::new(rectangle.left(),  rectangle.top()),
+            Vector2::new(rectangle.right(), rectangle.top()),
+            Vector2::new(rectangle.right(), rectangle.bottom()),
+            Vector2::new(rectangle.left(),  rectangle.bottom()),
+        ];
 
-        let model_matrix = Matrix4::from_translation(Vector3 { x: rectangle.centre().x, y: rectangle.centre().y,  z: 0.0 });
+        let inner = [
+            Vector2::new(rectangle.left()  + thickness, rectangle.top()    + thickness),
+            Vector2::new(rectangle.right() - thickness, rectangle.top()    + thickness),
+            Vector2::new(rectangle.right() - thickness, rectangle.bottom() - thickness),
+            Vector2::new(rectangle.left()  + thickness, rectangle.bottom() - thickness),
+        ];
+
+        self.push_ring(&outer, &inner, colour);
+    }
+
+    pub fn fill_rectangle(&mut self, rectangle: &Rectangle, colour: Colour)
+    {
+        if !self.batch_began {
+            panic!("You can't draw before calling begin");
+        }
 
-        let final_matrix = self.graphics_interface.world_matrix * self.camera_matrix  * model_matrix;
+        let corners = [
+            Vector2::new(rectangle.left(),  rectangle.top()),
+            Vector2::new(rectangle.right(), rectangle.top()),
+            Vector2::new(rectangle.right(), rectangle.bottom()),
+            Vector2::new(rectangle.left(),  rectangle.bottom()),
+        ];
 
-        let vertex_position_1 =  final_matrix * Vector4 { x: -origin_x, y:  -origin_y,  z: 0.0, w: 1.0 };
-        let vertex_position_2 =  final_matrix * Vector4 { x: -origin_x, y:   origin_y,  z: 0.0, w: 1.0 };
-        let vertex_position_3 =  final_matrix * Vector4 { x:  origin_x, y:   origin_y,  z: 0.0, w: 1.0 };
-        let vertex_position_4 =  final_matrix * Vector4 { x:  origin_x, y:  -origin_y,  z: 0.0, w: 1.0 };
+        self.push_shape(&corners, &[0, 1, 2, 0, 2, 3], colour);
+    }
 
-        let vertex_1 = ShapeVertex { position: [ vertex_position_1.x, vertex_position_1.y], color: [color.r as f32, color.g as f32, color.b as f32, color.a as f32] }; // bottom left
-        let vertex_2 = ShapeVertex { position: [ vertex_position_2.x, vertex_position_2.y], color: [color.r as f32, color.g as f32, color.b as f32, color.a as f32] }; // top left
-        let vertex_3 = ShapeVertex { position: [ vertex_position_3.x, vertex_position_3.y], color: [color.r as f32, color.g as f32, color.b as f32, color.a as f32] }; // top right
-        let vertex_4 = ShapeVertex { position: [ vertex_position_4.x, vertex_position_4.y], color: [color.r as f32, color.g as f32, color.b as f32, color.a as f32] }; // bottom right
-        
-        self.rectangle_vertices.push(vertex_1);
-        self.rectangle_vertices.push(vertex_2);
-        self.rectangle_vertices.push(vertex_3);
-        self.rectangle_vertices.push(vertex_4);
+    /// Outline of `circle`, `thickness` pixels wide and drawn inside its radius.
+    pub fn circle(&mut self, circle: &Circle, thickness: f32, colour: Colour)
+    {
+        if !self.batch_began {
+            panic!("You can't draw before calling begin");
+        }
 
-        let index_offset = 4 * self.rectangle_draw_count;
+        let inner_radius = (circle.radius - thickness).max(0.0);
 
-        self.rectangle_indices.push(0 + index_offset);
-        self.rectangle_indices.push(1 + index_offset);
-        self.rectangle_indices.push(1 + index_offset);
-        self.rectangle_indices.push(2 + index_offset);
-        self.rectangle_indices.push(2 + index_offset);
-        self.rectangle_indices.push(3 + index_offset);
-        self.rectangle_indices.push(3 + index_offset);
-        self.rectangle_indices.push(0 + index_offset);
+        let outer = circle_points(circle.position, circle.radius, self.circle_segments);
+        let inner = circle_points(circle.position, inner_radius, self.circle_segments);
 
-        self.rectangle_draw_count += 1;
+        self.push_ring(&outer, &inner, colour);
     }
 
-    pub fn circle(&mut self, circle: &Circle, colour: Colour)
+    pub fn fill_circle(&mut self, circle: &Circle, colour: Colour)
     {
         if !self.batch_began {
-            panic!("You can't call begin twice in a row");
+            panic!("You can't draw before calling begin");
         }
-        let color = colour.converted_to_color();
 
-        let model_matrix = Matrix4::from_translation(Vector3 { x: circle.position.x, y: circle.position.y,  z: 0.0 });
-        let final_matrix = self.graphics_interface.world_matrix * self.camera_matrix  * model_matrix;
+        let mut points = circle_points(circle.position, circle.radius, self.circle_segments);
+        points.push(circle.position);
 
-        let mut vertex_index:f32 = 0.0;
+        let centre = self.circle_segments as u32;
+        let mut indices: Vec<u32> = Vec::with_capacity(self.circle_segments * 3);
 
-        while vertex_index < 33.0
+        for index in 0..centre
         {
-            let vertex_position = final_matrix * Vector4 { x: ((vertex_index * (PI / 16.0))).cos() * circle.radius, y: ((vertex_index * (PI / 16.0))).sin() * circle.radius, z: 0.0, w: 1.0 };
-            let vertex = ShapeVertex { position: [ vertex_position.x, vertex_position.y], color: [color.r as f32, color.g as f32, color.b as f32, color.a as f32] };
+            indices.push(centre);
+            indices.push(index);
+            indices.push((index + 1) % centre);
+        }
+
+        self.push_shape(&points, &indices, colour);
+    }
 
-            self.circle_vertices.push(vertex);
-            vertex_index += 1.0;
+    /// Number of straight edges circles are built from, 32 by default.
+    pub fn set_circle_segments(&mut self, segments: usize) {
+        self.circle_segments = segments.max(3);
+    }
+
+    pub fn line(&mut self, start: Vector2<f32>, end: Vector2<f32>, thickness: f32, colour: Colour) {
+        self.polyline(&[start, end], thickness, colour);
+    }
+
+    /// Connected line segments through `points`, left open at the ends.
+    pub fn polyline(&mut self, points: &[Vector2<f32>], thickness: f32, colour: Colour)
+    {
+        if !self.batch_began {
+            panic!("You can't draw before calling begin");
         }
 
-        let mut index = 0;
-        let index_offset = 33 * self.circle_draw_count;
+        let (stroke_points, indices) = tessellation::stroke(points, thickness, false);
+        self.push_shape(&stroke_points, &indices, colour);
+    }
 
-        while index < 32
-        {
-            self.circle_indices.push(index + index_offset);
-            self.circle_indices.push((index + 1) + index_offset);
+    pub fn triangle(&mut self, a: Vector2<f32>, b: Vector2<f32>, c: Vector2<f32>, colour: Colour)
+    {
+        if !self.batch_began {
+            panic!("You can't draw before calling begin");
+        }
+
+        self.push_shape(&[a, b, c], &[0, 1, 2], colour);
+    }
+
+    /// Fills the polygon outlined by `points`, which may be concave but shouldn't cross itself.
+    pub fn polygon(&mut self, points: &[Vector2<f32>], colour: Colour)
+    {
+        if !self.batch_began {
+            panic!("You can't draw before calling begin");
+        }
+
+        let indices = tessellation::triangulate(points);
+        self.push_shape(points, &indices, colour);
+    }
+
+    pub fn polygon_outline(&mut self, points: &[Vector2<f32>], thickness: f32, colour: Colour)
+    {
+        if !self.batch_began {
+            panic!("You can't draw before calling begin");
+        }
+
+        let (stroke_points, indices) = tessellation::stroke(points, thickness, true);
+        self.push_shape(&stroke_points, &indices, colour);
+    }
+
+    fn push_ring(&mut self, outer: &[Vector2<f32>], inner: &[Vector2<f32>], colour: Colour)
+    {
+        let mut points = Vec::with_capacity(outer.len() * 2);
+        points.extend_from_slice(outer);
+        points.extend_from_slice(inner);
+
+        let indices = tessellation::ring_indices(outer.len() as u32);
+        self.push_shape(&points, &indices, colour);
+    }
+
+    /// Adds a triangle list to the shape batch, `indices` are relative to `points`.
+    fn push_shape(&mut self, points: &[Vector2<f32>], indices: &[u32], colour: Colour)
+    {
+        // Degenerate shapes, like a zero length line, tessellate to nothing.
+        if indices.is_empty() {
+            return;
+        }
 
-            index += 1;
+        let color = colour.converted_to_color();
+        let final_matrix = self.projection_matrix * self.camera_matrix;
+
+        let index_offset = self.shape_vertices.len() as u32;
+        let index_start = self.shape_indices.len();
+        let mut bottom = f32::MIN;
+
+        for point in points
+        {
+            let vertex_position = final_matrix * Vector4 { x: point.x, y: point.y, z: 0.0, w: 1.0 };
+            self.shape_vertices.push(ShapeVertex { position: [ vertex_position.x, vertex_position.y], color: [color.r as f32, color.g as f32, color.b as f32, color.a as f32] });
+            bottom = bottom.max(point.y);
         }
 
-        self.circle_indices.push(32 + index_offset);
-        self.circle_indices.push(0 + index_offset);
+        for index in indices {
+            self.shape_indices.push(index + index_offset);
+        }
 
-        self.circle_draw_count += 1;
+        let indices = index_start..self.shape_indices.len();
+        self.items.push(DrawItem { kind: DrawItemKind::Shape { indices }, layer_depth: self.layer_depth, bottom, blend_mode: self.blend_mode });
     }
 
     pub fn end(&mut self)
@@ -239,35 +563,157 @@ impl Draw
             panic!("You can't call end if without calling begin first");
         }
 
-        let mut x = 0;
-        let count = 16 - self.texture_index;
+        let order = self.sorted_items();
+        self.build_commands(&order);
+
+        self.graphics_interface.batch_render(&BatchData 
+        {
+            sprite_vertices: &self.sprite_vertices,
+            sprite_indices: &self.sprite_indices,
+            shape_vertices: &self.shape_vertices,
+            shape_indices: &self.ordered_shape_indices,
+            commands: &self.commands,
+            material: self.batch_settings.material.as_deref(),
+            viewport: self.batch_settings.viewport,
+            scissor: self.batch_settings.scissor,
+            render_target: self.batch_settings.render_target.as_ref().map(|render_target| render_target.texture.as_ref()),
+        });
+        self.flush();
+        self.batch_began = false;
+    }
+
+    /// Indices into `items` in the order the batch's sort mode wants them drawn.
+    fn sorted_items(&self) -> Vec<usize>
+    {
+        let items = &self.items;
+        let mut order: Vec<usize> = (0..items.len()).collect();
 
-        while x < count
+        match self.batch_settings.sort_mode
         {
-            self.texture_vec.push(self.dummy_texture.clone());
-            x += 1;
+            SortMode::Deferred => {},
+            SortMode::BackToFront => order.sort_by(|&a, &b| items[b].layer_depth.total_cmp(&items[a].layer_depth)),
+            SortMode::FrontToBack => order.sort_by(|&a, &b| items[a].layer_depth.total_cmp(&items[b].layer_depth)),
+            SortMode::YSort => order.sort_by(|&a, &b| items[a].bottom.total_cmp(&items[b].bottom)),
+            SortMode::Texture => order.sort_by_key(|&a| match items[a].kind
+            {
+                DrawItemKind::Sprite { ref texture, .. } => Some(texture.id),
+                DrawItemKind::Shape { .. } => None,
+            }),
         }
 
-        self.graphics_interface.batch_render(&self.texture_vec, &self.sprite_vertices, &self.sprite_indices, 
-                                                       &self.rectangle_vertices, &self.rectangle_indices,
-                                                       &self.circle_vertices, &self.circle_indices);
-        self.flush();
+        order
+    }
+
+    /// Lays the items out as index lists in draw order, with a command for each run that shares a pipeline
+    /// and blend mode. Sprite runs are also cut whenever they would need more textures than a draw can bind,
+    /// `MAX_BATCH_TEXTURES` or just one on downlevel adapters.
+    fn build_commands(&mut self, order: &[usize])
+    {
+        let items = std::mem::take(&mut self.items);
+        let render_target_id = self.batch_settings.render_target.as_ref().map(|render_target| render_target.texture.id);
+        let mut dropped_sprites = 0;
+
+        for &item_index in order
+        {
+            match &items[item_index].kind
+            {
+                // Sampling the texture the batch renders into is a usage conflict wgpu panics on.
+                DrawItemKind::Sprite { texture, .. } if Some(texture.id) == render_target_id => dropped_sprites += 1,
+                DrawItemKind::Sprite { texture, vertex_start } =>
+                {
+                    let blend_mode = items[item_index].blend_mode;
+                    let is_sprite_run = matches!(self.commands.last(), Some(BatchCommand::Sprites { blend_mode: run_blend_mode, .. }) if *run_blend_mode == blend_mode);
+                    let needs_slot = !self.texture_hashmap.contains_key(&texture.id);
+
+                    if !is_sprite_run || (needs_slot && self.texture_vec.len() == self.batch_texture_slots)
+                    {
+                        self.finish_sprite_command();
+
+                        let start = self.sprite_indices.len() as u32;
+                        self.commands.push(BatchCommand::Sprites { indices: start..start, textures: Vec::new(), blend_mode });
+                    }
+
+                    let texture_slot = match self.texture_hashmap.get(&texture.id)
+                    {
+                        Some(texture_slot) => *texture_slot,
+                        None =>
+                        {
+                            let texture_slot = self.texture_vec.len() as u32;
+                            self.texture_hashmap.insert(texture.id, texture_slot);
+                            self.texture_vec.push(Arc::clone(texture));
+                            texture_slot
+                        }
+                    };
+
+                    let vertex_start = *vertex_start;
+
+                    for vertex in &mut self.sprite_vertices[vertex_start as usize..vertex_start as usize + 4] {
+                        vertex.index = texture_slot;
+                    }
+
+                    for offset in [0, 1, 3, 1, 2, 3] {
+                        self.sprite_indices.push(vertex_start + offset);
+                    }
+
+                    if let Some(BatchCommand::Sprites { indices, .. }) = self.commands.last_mut() {
+                        indices.end = self.sprite_indices.len() as u32;
+                    }
+                }
+                DrawItemKind::Shape { indices } =>
+                {
+                    let start = self.ordered_shape_indices.len() as u32;
+                    self.ordered_shape_indices.extend_from_slice(&self.shape_indices[indices.clone()]);
+                    let end = self.ordered_shape_indices.len() as u32;
+
+                    self.finish_sprite_command();
+
+                    let blend_mode = items[item_index].blend_mode;
+
+                    match self.commands.last_mut()
+                    {
+                        Some(BatchCommand::Shapes { indices, blend_mode: run_blend_mode }) if *run_blend_mode == blend_mode => indices.end = end,
+                        _ => self.commands.push(BatchCommand::Shapes { indices: start..end, blend_mode }),
+                    }
+                }
+            }
+        }
+
+        self.finish_sprite_command();
+        self.items = items;
+
+        if dropped_sprites > 0 {
+            warn!("Dropped {} sprites drawing the render target their batch draws into", dropped_sprites);
+        }
+    }
+
+    /// Hands the textures gathered for the current sprite run to its command, padding the unused slots.
+    fn finish_sprite_command(&mut self)
+    {
+        if self.texture_vec.is_empty() {
+            return;
+        }
+
+        while self.texture_vec.len() < self.batch_texture_slots {
+            self.texture_vec.push(Arc::clone(&self.dummy_texture));
+        }
+
+        if let Some(BatchCommand::Sprites { textures, .. }) = self.commands.last_mut() {
+            *textures = std::mem::take(&mut self.texture_vec);
+        }
+
+        self.texture_hashmap.clear();
     }
 
     fn flush(& mut self)
     {
-        self.circle_draw_count = 0;
-        self.sprite_draw_count = 0;
-        self.rectangle_draw_count = 0;
-        self.circle_vertices.clear();
-        self.circle_indices.clear();
-        self.rectangle_vertices.clear();
-        self.rectangle_indices.clear();
+        self.items.clear();
+        self.commands.clear();
+        self.shape_vertices.clear();
+        self.shape_indices.clear();
+        self.ordered_shape_indices.clear();
         self.sprite_vertices.clear();
         self.sprite_indices.clear();
-        self.texture_index = 0;
         self.texture_vec.clear();
-        self.batch_began = false;
         self.texture_hashmap.clear();
     }
 }
\ No newline at end of file
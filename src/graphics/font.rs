@@ -0,0 +1,249 @@
+use std::fs;
+use log::warn;
+use std::sync::Arc;
+use std::path::Path;
+use std::num::NonZeroU32;
+use std::collections::HashMap;
+use image::{GenericImage, Rgba, RgbaImage};
+use crate::math::Vector2;
+use crate::shapes::rectangle::Rectangle;
+use crate::graphics::texture::Texture;
+use crate::platform::graphics_interface::GraphicsInterface;
+
+const INITIAL_ATLAS_SIZE: u32 = 256;
+const MAX_ATLAS_SIZE: u32 = 4096;
+
+/// Space left around every glyph in the atlas so linear filtering doesn't pick up its neighbours.
+const GLYPH_PADDING: u32 = 1;
+
+/// Sizes are rounded to a quarter pixel, so text that animates its size can't fill the atlas
+/// with a copy of every glyph at every size it passes through.
+const SIZE_STEPS_PER_PIXEL: f32 = 4.0;
+
+/// Where a rasterised glyph lives in the atlas and how it sits on the baseline.
+#[derive(Copy, Clone, Debug)]
+pub struct Glyph
+{
+    pub atlas_position: Vector2<u32>,
+    pub width: u32,
+    pub height: u32,
+    /// Offset from the pen position on the baseline to the top left of the bitmap.
+    pub offset: Vector2<f32>,
+    pub advance: f32,
+}
+
+/// A TrueType or OpenType font, glyphs are rasterised on the CPU the first time they are
+/// drawn at a size and packed into a texture atlas that grows when it runs out of room.
+pub struct Font
+{
+    font: fontdue::Font,
+    atlas: Arc<Texture>,
+    atlas_image: RgbaImage,
+    glyphs: HashMap<(char, u32), Glyph>,
+    shelf_x: u32,
+    shelf_y: u32,
+    shelf_height: u32,
+}
+
+impl Font
+{
+    pub fn new_from_file(graphics_interface: &GraphicsInterface, file_path: &str) -> Self
+    {
+        let data = match fs::read(Path::new(file_path))
+        {
+            Ok(data) => data,
+            Err(error) => panic!("Couldn't read font {}: {}", file_path, error),
+        };
+
+        Self::new_from_bytes(graphics_interface, &data)
+    }
+
+    pub fn new_from_bytes(graphics_interface: &GraphicsInterface, bytes: &[u8]) -> Self
+    {
+        let font = match fontdue::Font::from_bytes(bytes, fontdue::FontSettings::default())
+        {
+            Ok(font) => font,
+            Err(error) => panic!("Couldn't load font: {}", error),
+        };
+
+        let atlas_image = RgbaImage::from_pixel(INITIAL_ATLAS_SIZE, INITIAL_ATLAS_SIZE, Rgba([255, 255, 255, 0]));
+        let atlas = Arc::new(Texture::new_from_buffer(graphics_interface, atlas_image.clone(), Vector2::new(INITIAL_ATLAS_SIZE, INITIAL_ATLAS_SIZE)));
+
+        Self { font, atlas, atlas_image, glyphs: HashMap::new(), shelf_x: 0, shelf_y: 0, shelf_height: 0 }
+    }
+
+    pub fn atlas(&self) -> Arc<Texture> {
+        self.atlas.clone()
+    }
+
+    /// Distance from the top of a line to its baseline.
+    pub fn ascent(&self, size: f32) -> f32
+    {
+        match self.font.horizontal_line_metrics(size)
+        {
+            Some(metrics) => metrics.ascent,
+            None => size,
+        }
+    }
+
+    /// Distance from one baseline to the next.
+    pub fn line_height(&self, size: f32) -> f32
+    {
+        match self.font.horizontal_line_metrics(size)
+        {
+            Some(metrics) => metrics.new_line_size,
+            None => size,
+        }
+    }
+
+    pub fn kerning(&self, left: char, right: char, size: f32) -> f32 {
+        self.font.horizontal_kern(left, right, size).unwrap_or(0.0)
+    }
+
+    /// Returns the glyph for `character` at `size` pixels, rasterising it into the atlas if needed.
+    pub fn glyph(&mut self, graphics_interface: &GraphicsInterface, character: char, size: f32) -> Glyph
+    {
+        let size_steps = (size * SIZE_STEPS_PER_PIXEL).round().max(0.0) as u32;
+        let key = (character, size_steps);
+
+        if let Some(glyph) = self.glyphs.get(&key) {
+            return *glyph;
+        }
+
+        let (metrics, coverage) = self.font.rasterize(character, size_steps as f32 / SIZE_STEPS_PER_PIXEL);
+        let mut width = metrics.width as u32;
+        let mut height = metrics.height as u32;
+
+        let atlas_position = if width == 0 || height == 0 {
+            Vector2::new(0, 0)
+        }
+        else
+        {
+            match self.pack(graphics_interface, width, height, &coverage)
+            {
+                Some(atlas_position) => atlas_position,
+                None =>
+                {
+                    warn!("Glyph {:?} at size {} doesn't fit in the font atlas, it won't be drawn", character, size);
+                    width = 0;
+                    height = 0;
+                    Vector2::new(0, 0)
+                }
+            }
+        };
+
+        let glyph = Glyph
+        {
+            atlas_position,
+            width,
+            height,
+            offset: Vector2::new(metrics.xmin as f32, -(metrics.ymin as f32 + height as f32)),
+            advance: metrics.advance_width,
+        };
+
+        self.glyphs.insert(key, glyph);
+        glyph
+    }
+
+    /// Area of the atlas that holds `glyph`, ready to be passed to `Draw::sprite_with`.
+    /// Glyphs are packed from the top of the atlas, this flips their place to match.
+    pub fn glyph_area(&self, glyph: &Glyph) -> Rectangle {
+        self.atlas.area_from_top(Vector2::new(glyph.atlas_position.x as f32, glyph.atlas_position.y as f32), glyph.width as f32, glyph.height as f32)
+    }
+
+    /// Places a glyph bitmap in the atlas, `None` when it is larger than the biggest atlas.
+    fn pack(&mut self, graphics_interface: &GraphicsInterface, width: u32, height: u32, coverage: &[u8]) -> Option<Vector2<u32>>
+    {
+        let padded_width = width + GLYPH_PADDING;
+        let padded_height = height + GLYPH_PADDING;
+
+        loop
+        {
+            let atlas_size = self.atlas_image.width();
+
+            if self.shelf_x + padded_width > atlas_size
+            {
+                self.shelf_x = 0;
+                self.shelf_y += self.shelf_height;
+                self.shelf_height = 0;
+            }
+
+            if self.shelf_x + padded_width <= atlas_size && self.shelf_y + padded_height <= atlas_size {
+                break;
+            }
+
+            if atlas_size * 2 <= MAX_ATLAS_SIZE {
+                self.grow(graphics_interface);
+            }
+            else if self.shelf_x == 0 && self.shelf_y == 0 {
+                return None;
+            }
+            else {
+                self.repack(graphics_interface);
+            }
+        }
+
+        let position = Vector2::new(self.shelf_x, self.shelf_y);
+        self.shelf_x += padded_width;
+        self.shelf_height = self.shelf_height.max(padded_height);
+
+        let mut bitmap = RgbaImage::new(width, height);
+
+        for (pixel, alpha) in bitmap.pixels_mut().zip(coverage) {
+            *pixel = Rgba([255, 255, 255, *alpha]);
+        }
+
+        if let Err(error) = self.atlas_image.copy_from(&bitmap, position.x, position.y) {
+            panic!("Couldn't copy glyph into the font atlas: {}", error);
+        }
+
+        graphics_interface.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &self.atlas.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x: position.x, y: position.y, z: 0 },
+            },
+            &bitmap,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(4 * width),
+                rows_per_image: NonZeroU32::new(height),
+            },
+            wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        );
+
+        Some(position)
+    }
+
+    /// Doubles the atlas, glyphs already packed keep their place so sprites drawn this
+    /// batch with the old texture are still correct.
+    fn grow(&mut self, graphics_interface: &GraphicsInterface)
+    {
+        let atlas_size = self.atlas_image.width() * 2;
+        let mut atlas_image = RgbaImage::from_pixel(atlas_size, atlas_size, Rgba([255, 255, 255, 0]));
+
+        if let Err(error) = atlas_image.copy_from(&self.atlas_image, 0, 0) {
+            panic!("Couldn't grow the font atlas: {}", error);
+        }
+
+        self.atlas = Arc::new(Texture::new_from_buffer(graphics_interface, atlas_image.clone(), Vector2::new(atlas_size, atlas_size)));
+        self.atlas_image = atlas_image;
+    }
+
+    /// Starts over in an empty atlas once it can't grow any more, glyphs are rasterised again as they
+    /// are drawn. Sprites drawn this batch keep the old texture, so they are still correct.
+    fn repack(&mut self, graphics_interface: &GraphicsInterface)
+    {
+        warn!("Font atlas is full at {}x{}, repacking the glyphs in use", MAX_ATLAS_SIZE, MAX_ATLAS_SIZE);
+
+        let atlas_size = self.atlas_image.width();
+        self.atlas_image = RgbaImage::from_pixel(atlas_size, atlas_size, Rgba([255, 255, 255, 0]));
+        self.atlas = Arc::new(Texture::new_from_buffer(graphics_interface, self.atlas_image.clone(), Vector2::new(atlas_size, atlas_size)));
+
+        self.glyphs.clear();
+        self.shelf_x = 0;
+        self.shelf_y = 0;
+        self.shelf_height = 0;
+    }
+}
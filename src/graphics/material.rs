@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::Path;
+use wgpu::util::DeviceExt;
+use crate::platform::graphics_interface::GraphicsInterface;
+
+/// Size the uniform buffer is rounded up to, also what a material without uniforms gets.
+const UNIFORM_ALIGNMENT: usize = 16;
+
+/// A custom fragment shader for sprites, selected for a batch with `BatchSettings::material`.
+///
+/// The WGSL has to define `fn fragment(in: VertexOutput) -> vec4<f32>`. It is compiled after a
+/// prelude that declares `VertexOutput` (`tex_coords`, `color` and the texture `index`) and the
+/// helpers `sample_texture(in)`, `sample_texture_at(in, tex_coords)` and `texture_size(in)`.
+/// Uniforms are declared by the shader as `@group(1) @binding(0) var<uniform> material: T;`,
+/// where `T` matches the layout of the value passed to `with_uniforms`.
+#[derive(Debug)]
+pub struct Material
+{
+    pub pipelines: Vec<wgpu::RenderPipeline>,
+    pub uniform_buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Material
+{
+    pub fn new(graphics_interface: &GraphicsInterface, fragment_source: &str) -> Self {
+        Self::with_uniforms(graphics_interface, fragment_source, &[0u8; UNIFORM_ALIGNMENT])
+    }
+
+    pub fn with_uniforms<T: bytemuck::Pod>(graphics_interface: &GraphicsInterface, fragment_source: &str, uniforms: &T) -> Self
+    {
+        let pipelines = graphics_interface.create_material_pipelines(fragment_source);
+
+        let mut contents = bytemuck::bytes_of(uniforms).to_vec();
+        contents.resize(wgpu::util::align_to(contents.len().max(1), UNIFORM_ALIGNMENT), 0);
+
+        let uniform_buffer = graphics_interface.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Material Uniform Buffer"),
+            contents: &contents,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group = graphics_interface.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            entries: &[
+                wgpu::BindGroupEntry 
+                {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+            layout: graphics_interface.material_bind_group_layout(),
+            label: Some("material bind group"),
+        });
+
+        Self { pipelines, uniform_buffer, bind_group }
+    }
+
+    pub fn new_from_file(graphics_interface: &GraphicsInterface, file_path: &str) -> Self {
+        Self::new(graphics_interface, &Self::read_source(file_path))
+    }
+
+    pub fn with_uniforms_from_file<T: bytemuck::Pod>(graphics_interface: &GraphicsInterface, file_path: &str, uniforms: &T) -> Self {
+        Self::with_uniforms(graphics_interface, &Self::read_source(file_path), uniforms)
+    }
+
+    /// Replaces the uniform values, batches ended after this call see the new values.
+    /// `uniforms` has to be the same type the material was created with.
+    pub fn set_uniforms<T: bytemuck::Pod>(&self, graphics_interface: &GraphicsInterface, uniforms: &T)
+    {
+        let mut contents = bytemuck::bytes_of(uniforms).to_vec();
+        contents.resize(wgpu::util::align_to(contents.len(), wgpu::COPY_BUFFER_ALIGNMENT as usize), 0);
+
+        graphics_interface.queue.write_buffer(&self.uniform_buffer, 0, &contents);
+    }
+
+    fn read_source(file_path: &str) -> String
+    {
+        match fs::read_to_string(Path::new(file_path))
+        {
+            Ok(source) => source,
+            Err(error) => panic!("Couldn't read material {}: {}", file_path, error),
+        }
+    }
+}
@@ -0,0 +1,19 @@
+pub mod vignette;
+pub mod scanlines;
+pub mod chromatic_aberration;
+
+/// A full screen pass run over the finished frame, added with `Draw::add_post_effect`.
+///
+/// The shader has to define `fn effect(uv: vec2<f32>) -> vec4<f32>`, it is compiled after a
+/// prelude with the helpers `sample_source(uv)` and `source_size()`. Uniforms are declared by
+/// the shader as `@group(1) @binding(0) var<uniform> name: T;` and filled from `uniforms`.
+pub trait PostEffect
+{
+    /// Read once, when the effect is added.
+    fn shader(&self) -> String;
+
+    /// Read every frame, so changing the effect's fields takes effect straight away.
+    fn uniforms(&self) -> Vec<u8> {
+        Vec::new()
+    }
+}
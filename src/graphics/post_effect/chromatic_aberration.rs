@@ -0,0 +1,41 @@
+use crate::graphics::post_effect::PostEffect;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ChromaticAberrationUniforms
+{
+    offset: f32,
+    padding: [f32; 3],
+}
+
+/// Splits the red and blue channels apart towards the edges of the frame.
+pub struct ChromaticAberration
+{
+    /// How far apart the channels are at the corners, in pixels.
+    pub offset: f32,
+}
+
+impl ChromaticAberration
+{
+    pub fn new() -> Self {
+        Self { offset: 2.0 }
+    }
+}
+
+impl Default for ChromaticAberration
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PostEffect for ChromaticAberration
+{
+    fn shader(&self) -> String {
+        include_str!("chromatic_aberration.wgsl").to_string()
+    }
+
+    fn uniforms(&self) -> Vec<u8> {
+        bytemuck::bytes_of(&ChromaticAberrationUniforms { offset: self.offset, padding: [0.0; 3] }).to_vec()
+    }
+}
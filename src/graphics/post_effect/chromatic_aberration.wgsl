@@ -0,0 +1,19 @@
+struct ChromaticAberration 
+{
+    offset: f32,
+    _padding0: f32,
+    _padding1: f32,
+    _padding2: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> aberration: ChromaticAberration;
+
+fn effect(uv: vec2<f32>) -> vec4<f32> 
+{
+    let from_centre = uv - vec2<f32>(0.5, 0.5);
+    let shift = from_centre * 2.0 * aberration.offset / source_size();
+    let source = sample_source(uv);
+
+    return vec4<f32>(sample_source(uv + shift).r, source.g, sample_source(uv - shift).b, source.a);
+}
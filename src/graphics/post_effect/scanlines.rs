@@ -0,0 +1,44 @@
+use crate::graphics::post_effect::PostEffect;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ScanlinesUniforms
+{
+    intensity: f32,
+    line_height: f32,
+    padding: [f32; 2],
+}
+
+/// Darkens alternating rows of the frame like an old CRT.
+pub struct Scanlines
+{
+    /// How dark the darkest part of a line gets, 0.0 to 1.0.
+    pub intensity: f32,
+    /// Height of one light and dark pair in pixels of the frame.
+    pub line_height: f32,
+}
+
+impl Scanlines
+{
+    pub fn new() -> Self {
+        Self { intensity: 0.25, line_height: 3.0 }
+    }
+}
+
+impl Default for Scanlines
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PostEffect for Scanlines
+{
+    fn shader(&self) -> String {
+        include_str!("scanlines.wgsl").to_string()
+    }
+
+    fn uniforms(&self) -> Vec<u8> {
+        bytemuck::bytes_of(&ScanlinesUniforms { intensity: self.intensity, line_height: self.line_height, padding: [0.0; 2] }).to_vec()
+    }
+}
@@ -0,0 +1,19 @@
+struct Scanlines 
+{
+    intensity: f32,
+    line_height: f32,
+    _padding0: f32,
+    _padding1: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> scanlines: Scanlines;
+
+fn effect(uv: vec2<f32>) -> vec4<f32> 
+{
+    let source = sample_source(uv);
+    let row = uv.y * source_size().y / scanlines.line_height;
+    let darkness = (0.5 - 0.5 * cos(row * 6.28318530718)) * scanlines.intensity;
+
+    return vec4<f32>(source.rgb * (1.0 - darkness), source.a);
+}
@@ -0,0 +1,61 @@
+use crate::graphics::colour::Colour;
+use crate::graphics::post_effect::PostEffect;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct VignetteUniforms
+{
+    colour: [f32; 4],
+    strength: f32,
+    radius: f32,
+    softness: f32,
+    padding: f32,
+}
+
+/// Fades the edges of the frame towards `colour`.
+pub struct Vignette
+{
+    pub colour: Colour,
+    /// 0.0 leaves the frame alone, 1.0 fully covers the corners.
+    pub strength: f32,
+    /// Distance from the centre, in screen heights, where the fade starts.
+    pub radius: f32,
+    /// Distance the fade takes to reach full strength.
+    pub softness: f32,
+}
+
+impl Vignette
+{
+    pub fn new() -> Self {
+        Self { colour: Colour::BLACK, strength: 0.6, radius: 0.4, softness: 0.5 }
+    }
+}
+
+impl Default for Vignette
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PostEffect for Vignette
+{
+    fn shader(&self) -> String {
+        include_str!("vignette.wgsl").to_string()
+    }
+
+    fn uniforms(&self) -> Vec<u8>
+    {
+        let color = self.colour.converted_to_color();
+        let uniforms = VignetteUniforms
+        {
+            colour: [color.r as f32, color.g as f32, color.b as f32, color.a as f32],
+            strength: self.strength,
+            radius: self.radius,
+            softness: self.softness,
+            padding: 0.0,
+        };
+
+        bytemuck::bytes_of(&uniforms).to_vec()
+    }
+}
@@ -0,0 +1,21 @@
+struct Vignette 
+{
+    colour: vec4<f32>,
+    strength: f32,
+    radius: f32,
+    softness: f32,
+    _padding: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> vignette: Vignette;
+
+fn effect(uv: vec2<f32>) -> vec4<f32> 
+{
+    let source = sample_source(uv);
+    let size = source_size();
+    let from_centre = (uv - vec2<f32>(0.5, 0.5)) * vec2<f32>(size.x / size.y, 1.0);
+    let amount = smoothstep(vignette.radius, vignette.radius + vignette.softness, length(from_centre)) * vignette.strength;
+
+    return vec4<f32>(mix(source.rgb, vignette.colour.rgb, amount), source.a);
+}
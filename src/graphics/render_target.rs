@@ -0,0 +1,61 @@
+use std::{iter, sync::Arc};
+use crate::math::{self, Matrix4};
+use crate::graphics::colour::Colour;
+use crate::graphics::texture::Texture;
+use crate::platform::graphics_interface::GraphicsInterface;
+
+/// A texture batches can draw into, selected with `BatchSettings::render_target`, that can then be
+/// drawn with `Draw::sprite` through `texture` like any other texture.
+///
+/// It keeps what was drawn into it until `clear` is called, so it also works as a cache for layers
+/// that rarely change. Sprites of the target drawn in a batch that draws into it are dropped with a warning.
+#[derive(Clone, Debug)]
+pub struct RenderTarget
+{
+    pub texture: Arc<Texture>,
+}
+
+impl RenderTarget
+{
+    pub fn new(graphics_interface: &GraphicsInterface, width: u32, height: u32) -> Self {
+        Self { texture: Arc::new(Texture::new_render_target(graphics_interface, width, height)) }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.texture.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.texture.height
+    }
+
+    /// Takes pixels to clip space for a batch covering the whole target.
+    pub fn projection_matrix(&self) -> Matrix4<f32> {
+        math::ortho(0.0, self.texture.width as f32, self.texture.height as f32, 0.0, -1.0, 1.0)
+    }
+
+    /// Fills the whole target with `colour`, straight away rather than at the next batch.
+    pub fn clear(&self, graphics_interface: &GraphicsInterface, colour: Colour)
+    {
+        let color = colour.converted_to_color();
+
+        let mut encoder = graphics_interface.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Render Target Clear Encoder"),
+        });
+
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Target Clear Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.texture.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color { r: color.r, g: color.g, b: color.b, a: color.a }),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        graphics_interface.queue.submit(iter::once(encoder.finish()));
+    }
+}
@@ -0,0 +1,63 @@
+use crate::math::Vector2;
+
+/// Per sprite transform used by `Draw::sprite_with`.
+#[derive(Copy, Clone, Debug)]
+pub struct SpriteOptions
+{
+    /// Point in the sprite, in pixels from its top left corner, that sits on the draw position
+    /// and that rotation and scale happen around. `None` uses the centre.
+    pub origin: Option<Vector2<f32>>,
+    /// Negative components mirror the sprite around the origin.
+    pub scale: Vector2<f32>,
+    pub rotation: f32,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// Layer depth for this sprite alone, `None` uses the one set with `Draw::set_layer_depth`.
+    pub layer_depth: Option<f32>,
+}
+
+impl SpriteOptions
+{
+    pub fn new() -> Self {
+        Self { origin: None, scale: Vector2::new(1.0, 1.0), rotation: 0.0, flip_x: false, flip_y: false, layer_depth: None }
+    }
+
+    pub fn origin(mut self, origin: Vector2<f32>) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    pub fn scale(mut self, scale: Vector2<f32>) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Mirrors the sprite left to right, the origin stays where it is.
+    pub fn flip_x(mut self, flip_x: bool) -> Self {
+        self.flip_x = flip_x;
+        self
+    }
+
+    /// Mirrors the sprite top to bottom, the origin stays where it is.
+    pub fn flip_y(mut self, flip_y: bool) -> Self {
+        self.flip_y = flip_y;
+        self
+    }
+
+    pub fn layer_depth(mut self, layer_depth: f32) -> Self {
+        self.layer_depth = Some(layer_depth);
+        self
+    }
+}
+
+impl Default for SpriteOptions
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
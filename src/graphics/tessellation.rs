@@ -0,0 +1,335 @@
+use std::f32::consts::PI;
+use crate::math::{Vector2, InnerSpace};
+
+/// How far a miter joint may stick out, in multiples of half the thickness, before it gets cut short.
+const MITER_LIMIT: f32 = 4.0;
+
+pub fn circle_points(centre: Vector2<f32>, radius: f32, segments: usize) -> Vec<Vector2<f32>>
+{
+    let mut points = Vec::with_capacity(segments);
+
+    for segment in 0..segments
+    {
+        let angle = segment as f32 * (2.0 * PI / segments as f32);
+        points.push(Vector2::new(centre.x + angle.cos() * radius, centre.y + angle.sin() * radius));
+    }
+
+    points
+}
+
+/// Triangle indices that fill the band between two closed outlines with the same number of points.
+pub fn ring_indices(count: u32) -> Vec<u32>
+{
+    let mut indices = Vec::with_capacity(count as usize * 6);
+
+    for index in 0..count
+    {
+        let next = (index + 1) % count;
+
+        indices.push(index);
+        indices.push(next);
+        indices.push(count + next);
+        indices.push(index);
+        indices.push(count + next);
+        indices.push(count + index);
+    }
+
+    indices
+}
+
+/// Turns a line through `points` into triangles `thickness` pixels wide, corners are mitered.
+pub fn stroke(points: &[Vector2<f32>], thickness: f32, closed: bool) -> (Vec<Vector2<f32>>, Vec<u32>)
+{
+    let points = remove_repeated_points(points, closed);
+    let count = points.len();
+
+    if count < 2 {
+        return (Vec::new(), Vec::new());
+    }
+
+    let half_thickness = thickness / 2.0;
+
+    let mut left = Vec::with_capacity(count);
+    let mut right = Vec::with_capacity(count);
+
+    for index in 0..count
+    {
+        let previous = if index > 0 { Some(points[index - 1]) } else if closed { Some(points[count - 1]) } else { None };
+        let next = if index + 1 < count { Some(points[index + 1]) } else if closed { Some(points[0]) } else { None };
+
+        let incoming = previous.map(|previous| normal(points[index] - previous));
+        let outgoing = next.map(|next| normal(next - points[index]));
+
+        let offset = match (incoming, outgoing)
+        {
+            (Some(incoming), Some(outgoing)) =>
+            {
+                let miter = incoming + outgoing;
+
+                if miter.magnitude2() < f32::EPSILON {
+                    outgoing * half_thickness
+                }
+                else
+                {
+                    let miter = miter.normalize();
+                    let length = (half_thickness / miter.dot(outgoing)).min(half_thickness * MITER_LIMIT);
+                    miter * length
+                }
+            }
+            (Some(normal), None) | (None, Some(normal)) => normal * half_thickness,
+            (None, None) => Vector2::new(0.0, 0.0),
+        };
+
+        left.push(points[index] + offset);
+        right.push(points[index] - offset);
+    }
+
+    let segment_count = if closed { count } else { count - 1 };
+    let mut indices = Vec::with_capacity(segment_count * 6);
+
+    for index in 0..segment_count
+    {
+        let current = index as u32;
+        let next = ((index + 1) % count) as u32;
+        let count = count as u32;
+
+        indices.push(current);
+        indices.push(next);
+        indices.push(count + next);
+        indices.push(current);
+        indices.push(count + next);
+        indices.push(count + current);
+    }
+
+    left.extend(right);
+    (left, indices)
+}
+
+/// Triangulates a simple polygon, convex or concave, by ear clipping.
+pub fn triangulate(points: &[Vector2<f32>]) -> Vec<u32>
+{
+    let count = points.len();
+
+    if count < 3 {
+        return Vec::new();
+    }
+
+    let mut remaining: Vec<u32> = (0..count as u32).collect();
+
+    if signed_area(points) < 0.0 {
+        remaining.reverse();
+    }
+
+    let mut indices = Vec::with_capacity((count - 2) * 3);
+
+    while remaining.len() > 3
+    {
+        let mut ear_found = false;
+
+        for index in 0..remaining.len()
+        {
+            let previous = remaining[(index + remaining.len() - 1) % remaining.len()];
+            let current = remaining[index];
+            let next = remaining[(index + 1) % remaining.len()];
+
+            if is_ear(points, &remaining, previous, current, next)
+            {
+                indices.extend_from_slice(&[previous, current, next]);
+                remaining.remove(index);
+                ear_found = true;
+                break;
+            }
+        }
+
+        // Self intersecting or degenerate outlines can run out of ears, fan out whatever is left.
+        if !ear_found
+        {
+            for index in 1..remaining.len() - 1 {
+                indices.extend_from_slice(&[remaining[0], remaining[index], remaining[index + 1]]);
+            }
+
+            return indices;
+        }
+    }
+
+    indices.extend_from_slice(&remaining);
+    indices
+}
+
+fn is_ear(points: &[Vector2<f32>], remaining: &[u32], previous: u32, current: u32, next: u32) -> bool
+{
+    let a = points[previous as usize];
+    let b = points[current as usize];
+    let c = points[next as usize];
+
+    if cross(b - a, c - b) <= 0.0 {
+        return false;
+    }
+
+    !remaining.iter()
+        .filter(|&&index| index != previous && index != current && index != next)
+        .any(|&index| point_in_triangle(points[index as usize], a, b, c))
+}
+
+fn point_in_triangle(point: Vector2<f32>, a: Vector2<f32>, b: Vector2<f32>, c: Vector2<f32>) -> bool
+{
+    let ab = cross(b - a, point - a);
+    let bc = cross(c - b, point - b);
+    let ca = cross(a - c, point - c);
+
+    ab >= 0.0 && bc >= 0.0 && ca >= 0.0
+}
+
+fn signed_area(points: &[Vector2<f32>]) -> f32
+{
+    let mut area = 0.0;
+
+    for index in 0..points.len()
+    {
+        let current = points[index];
+        let next = points[(index + 1) % points.len()];
+        area += current.x * next.y - next.x * current.y;
+    }
+
+    area / 2.0
+}
+
+fn remove_repeated_points(points: &[Vector2<f32>], closed: bool) -> Vec<Vector2<f32>>
+{
+    let mut unique: Vec<Vector2<f32>> = Vec::with_capacity(points.len());
+
+    for point in points
+    {
+        let is_repeated = match unique.last()
+        {
+            Some(last) => (*point - *last).magnitude2() <= f32::EPSILON,
+            None => false,
+        };
+
+        if !is_repeated {
+            unique.push(*point);
+        }
+    }
+
+    if closed && unique.len() > 1 && (unique[0] - unique[unique.len() - 1]).magnitude2() <= f32::EPSILON {
+        unique.pop();
+    }
+
+    unique
+}
+
+fn cross(a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+fn normal(direction: Vector2<f32>) -> Vector2<f32> {
+    Vector2::new(-direction.y, direction.x).normalize()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn triangle_areas(points: &[Vector2<f32>], indices: &[u32]) -> Vec<f32>
+    {
+        indices.chunks(3)
+            .map(|triangle| signed_area(&[points[triangle[0] as usize], points[triangle[1] as usize], points[triangle[2] as usize]]))
+            .collect()
+    }
+
+    fn l_shape() -> Vec<Vector2<f32>>
+    {
+        vec![Vector2::new(0.0, 0.0), Vector2::new(2.0, 0.0), Vector2::new(2.0, 1.0),
+             Vector2::new(1.0, 1.0), Vector2::new(1.0, 2.0), Vector2::new(0.0, 2.0)]
+    }
+
+    #[test]
+    fn concave_polygon_is_covered_exactly()
+    {
+        let points = l_shape();
+        let areas = triangle_areas(&points, &triangulate(&points));
+
+        assert_eq!(areas.len(), points.len() - 2);
+        assert!(areas.iter().all(|&area| area > 0.0));
+        assert!((areas.iter().sum::<f32>() - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn clockwise_polygon_gives_the_same_winding()
+    {
+        let mut points = l_shape();
+        points.reverse();
+
+        let areas = triangle_areas(&points, &triangulate(&points));
+
+        assert_eq!(areas.len(), points.len() - 2);
+        assert!(areas.iter().all(|&area| area > 0.0));
+        assert!((areas.iter().sum::<f32>() - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn self_intersecting_polygon_falls_back_to_a_fan()
+    {
+        let points = [Vector2::new(2.0, 2.0), Vector2::new(6.0, 3.0), Vector2::new(1.0, 0.0),
+                      Vector2::new(5.0, 1.0), Vector2::new(5.0, 5.0), Vector2::new(2.0, 0.0)];
+
+        let indices = triangulate(&points);
+
+        assert_eq!(indices.len(), (points.len() - 2) * 3);
+        assert!(indices.iter().all(|&index| (index as usize) < points.len()));
+    }
+
+    #[test]
+    fn collinear_polygon_still_terminates()
+    {
+        let points = [Vector2::new(0.0, 0.0), Vector2::new(1.0, 0.0), Vector2::new(2.0, 0.0), Vector2::new(3.0, 0.0)];
+
+        assert_eq!(triangulate(&points).len(), (points.len() - 2) * 3);
+    }
+
+    #[test]
+    fn fewer_than_three_points_triangulate_to_nothing()
+    {
+        assert!(triangulate(&[]).is_empty());
+        assert!(triangulate(&[Vector2::new(0.0, 0.0), Vector2::new(1.0, 0.0)]).is_empty());
+    }
+
+    #[test]
+    fn sharp_corner_is_cut_at_the_miter_limit()
+    {
+        let thickness = 2.0;
+        let points = [Vector2::new(0.0, 0.0), Vector2::new(10.0, 0.0), Vector2::new(0.0, 1.0)];
+
+        let (vertices, indices) = stroke(&points, thickness, false);
+        let corner_offset = (vertices[1] - points[1]).magnitude();
+
+        assert_eq!(vertices.len(), points.len() * 2);
+        assert_eq!(indices.len(), 2 * 6);
+        assert!((corner_offset - thickness / 2.0 * MITER_LIMIT).abs() < 1e-4);
+    }
+
+    #[test]
+    fn gentle_corner_keeps_its_full_miter()
+    {
+        let thickness = 2.0;
+        let points = [Vector2::new(0.0, 0.0), Vector2::new(10.0, 0.0), Vector2::new(20.0, 10.0)];
+
+        let (vertices, _) = stroke(&points, thickness, false);
+        let corner_offset = (vertices[1] - points[1]).magnitude();
+        let half_angle = (PI / 8.0).cos();
+
+        assert!((corner_offset - thickness / 2.0 / half_angle).abs() < 1e-4);
+    }
+
+    #[test]
+    fn stroke_of_a_single_point_is_empty()
+    {
+        let point = Vector2::new(4.0, 4.0);
+
+        let (vertices, indices) = stroke(&[point, point], 2.0, false);
+
+        assert!(vertices.is_empty());
+        assert!(indices.is_empty());
+    }
+}
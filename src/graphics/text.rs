@@ -0,0 +1,84 @@
+pub mod layout;
+pub mod markup;
+pub mod typewriter;
+
+use std::sync::Arc;
+use crate::math::Vector2;
+use crate::shapes::rectangle::Rectangle;
+use crate::graphics::font::Font;
+use crate::graphics::texture::Texture;
+use crate::graphics::bitmap_font::BitmapFont;
+use crate::platform::graphics_interface::GraphicsInterface;
+
+/// Everything needed to place one character, `texture` is `None` for blank glyphs like spaces.
+pub struct GlyphQuad
+{
+    pub texture: Option<Arc<Texture>>,
+    /// Draw area in `texture`, fonts flip their top down atlas places with `Texture::area_from_top`.
+    pub area: Rectangle,
+    /// Offset from the pen position on the top of the line to the top left of the quad.
+    pub offset: Vector2<f32>,
+    pub size: Vector2<f32>,
+    pub advance: f32,
+}
+
+/// Fonts the text layout can work with. For a `Font` `size` is in pixels, for a `BitmapFont`
+/// it scales the glyphs as drawn in the page texture.
+pub trait TextFont
+{
+    fn line_height(&self, size: f32) -> f32;
+    fn kerning(&self, left: char, right: char, size: f32) -> f32;
+    fn glyph_quad(&mut self, graphics_interface: &GraphicsInterface, character: char, size: f32) -> Option<GlyphQuad>;
+}
+
+impl TextFont for Font
+{
+    fn line_height(&self, size: f32) -> f32 {
+        Font::line_height(self, size)
+    }
+
+    fn kerning(&self, left: char, right: char, size: f32) -> f32 {
+        Font::kerning(self, left, right, size)
+    }
+
+    fn glyph_quad(&mut self, graphics_interface: &GraphicsInterface, character: char, size: f32) -> Option<GlyphQuad>
+    {
+        let glyph = self.glyph(graphics_interface, character, size);
+        let texture = if glyph.width > 0 && glyph.height > 0 { Some(self.atlas()) } else { None };
+
+        Some(GlyphQuad
+        {
+            texture,
+            area: self.glyph_area(&glyph),
+            offset: Vector2::new(glyph.offset.x, self.ascent(size) + glyph.offset.y),
+            size: Vector2::new(glyph.width as f32, glyph.height as f32),
+            advance: glyph.advance,
+        })
+    }
+}
+
+impl TextFont for BitmapFont
+{
+    fn line_height(&self, size: f32) -> f32 {
+        self.line_height * size
+    }
+
+    fn kerning(&self, left: char, right: char, size: f32) -> f32 {
+        BitmapFont::kerning(self, left, right) * size
+    }
+
+    fn glyph_quad(&mut self, _graphics_interface: &GraphicsInterface, character: char, size: f32) -> Option<GlyphQuad>
+    {
+        let glyph = *self.glyph(character)?;
+        let texture = if glyph.width > 0.0 && glyph.height > 0.0 { Some(self.pages[glyph.page].clone()) } else { None };
+
+        Some(GlyphQuad
+        {
+            texture,
+            area: self.glyph_area(&glyph),
+            offset: glyph.offset * size,
+            size: Vector2::new(glyph.width, glyph.height) * size,
+            advance: glyph.advance * size,
+        })
+    }
+}
@@ -0,0 +1,337 @@
+use std::f64::consts::TAU;
+use crate::math::Vector2;
+use crate::graphics::colour::Colour;
+use crate::graphics::text::{TextFont, markup};
+use crate::platform::graphics_interface::GraphicsInterface;
+
+/// How far apart, in radians, neighbouring characters are along a `[wave]`.
+const WAVE_PHASE_PER_CHARACTER: f32 = 0.5;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextAlignment
+{
+    Left,
+    Centre,
+    Right,
+    /// Stretches the spaces so every line but the last of a paragraph fills the width.
+    Justify,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct TextSettings
+{
+    pub size: f32,
+    /// Lines longer than this are wrapped between words, `None` only breaks on `\n`.
+    pub wrap_width: Option<f32>,
+    pub alignment: TextAlignment,
+    /// Characters past this are hidden, markup tags aren't counted.
+    pub visible_characters: Option<usize>,
+    /// Seconds that drive the `[wave]` animation, usually `GameTime::total_time`.
+    pub time: f64,
+    pub wave_amplitude: f32,
+    pub wave_speed: f32,
+}
+
+impl TextSettings
+{
+    pub fn new(size: f32) -> Self {
+        Self { size, wrap_width: None, alignment: TextAlignment::Left, visible_characters: None, time: 0.0, wave_amplitude: 3.0, wave_speed: 6.0 }
+    }
+
+    pub fn wrap_width(mut self, wrap_width: f32) -> Self {
+        self.wrap_width = Some(wrap_width);
+        self
+    }
+
+    pub fn alignment(mut self, alignment: TextAlignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    pub fn visible_characters(mut self, visible_characters: usize) -> Self {
+        self.visible_characters = Some(visible_characters);
+        self
+    }
+
+    pub fn time(mut self, time: f64) -> Self {
+        self.time = time;
+        self
+    }
+
+    pub fn wave(mut self, amplitude: f32, speed: f32) -> Self {
+        self.wave_amplitude = amplitude;
+        self.wave_speed = speed;
+        self
+    }
+
+    /// Vertical offset of the character at `index` inside a `[wave]`.
+    pub fn wave_offset(&self, index: usize) -> f32
+    {
+        // Wrapped while still in f64 so the wave stays smooth however long the game has been running.
+        let phase = (self.time * self.wave_speed as f64) % TAU;
+        (phase as f32 + index as f32 * WAVE_PHASE_PER_CHARACTER).sin() * self.wave_amplitude
+    }
+}
+
+/// A character placed by `TextLayout`, `position` is the pen on the top of its line.
+#[derive(Copy, Clone, Debug)]
+pub struct LaidOutGlyph
+{
+    pub character: char,
+    pub position: Vector2<f32>,
+    pub colour: Option<Colour>,
+    pub wave: bool,
+    /// Position in the text with markup removed, used for the typewriter reveal.
+    pub index: usize,
+}
+
+struct Line
+{
+    start: usize,
+    end: usize,
+    ends_paragraph: bool,
+}
+
+/// Marked up text broken into lines and positioned relative to its top left corner.
+/// Keep it around while the text and settings don't change instead of laying out every frame.
+pub struct TextLayout
+{
+    pub glyphs: Vec<LaidOutGlyph>,
+    /// Width of the widest line and height of all lines.
+    pub size: Vector2<f32>,
+    pub font_size: f32,
+    pub character_count: usize,
+}
+
+impl TextLayout
+{
+    pub fn new(font: &mut impl TextFont, graphics_interface: &GraphicsInterface, text: &str, settings: &TextSettings) -> Self
+    {
+        let characters = markup::parse(text);
+        let size = settings.size;
+
+        let advances: Vec<f32> = characters.iter()
+            .map(|styled| match font.glyph_quad(graphics_interface, styled.character, size)
+            {
+                Some(quad) if styled.character != '\n' => quad.advance,
+                _ => 0.0,
+            })
+            .collect();
+
+        let width = |start: usize, end: usize| -> f32
+        {
+            let mut width = 0.0;
+
+            for index in start..end
+            {
+                if index > start {
+                    width += font.kerning(characters[index - 1].character, characters[index].character, size);
+                }
+
+                width += advances[index];
+            }
+
+            width
+        };
+
+        let lines = break_lines(&characters, settings.wrap_width, &width);
+        let line_widths: Vec<f32> = lines.iter().map(|line| width(line.start, line.end)).collect();
+        let widest_line = line_widths.iter().fold(0.0_f32, |widest, line_width| widest.max(*line_width));
+        let available_width = settings.wrap_width.unwrap_or(widest_line);
+        let line_height = font.line_height(size);
+
+        let mut glyphs = Vec::with_capacity(characters.len());
+        let mut layout_width = 0.0_f32;
+
+        for (line_index, line) in lines.iter().enumerate()
+        {
+            let spare_width = (available_width - line_widths[line_index]).max(0.0);
+            let spaces = characters[line.start..line.end].iter().filter(|styled| styled.character.is_whitespace()).count();
+
+            let (mut pen_x, space_stretch) = align(settings.alignment, spare_width, spaces, line.ends_paragraph);
+
+            let pen_y = line_index as f32 * line_height;
+
+            for index in line.start..line.end
+            {
+                let styled = characters[index];
+
+                if index > line.start {
+                    pen_x += font.kerning(characters[index - 1].character, styled.character, size);
+                }
+
+                if styled.character.is_whitespace() {
+                    pen_x += space_stretch;
+                }
+                else {
+                    glyphs.push(LaidOutGlyph { character: styled.character, position: Vector2::new(pen_x, pen_y), colour: styled.colour, wave: styled.wave, index });
+                }
+
+                pen_x += advances[index];
+            }
+
+            layout_width = layout_width.max(pen_x);
+        }
+
+        Self
+        {
+            glyphs,
+            size: Vector2::new(layout_width, lines.len() as f32 * line_height),
+            font_size: size,
+            character_count: characters.len(),
+        }
+    }
+}
+
+/// Where a line starts and how much each of its spaces is widened, given the width it doesn't use.
+fn align(alignment: TextAlignment, spare_width: f32, spaces: usize, ends_paragraph: bool) -> (f32, f32)
+{
+    match alignment
+    {
+        TextAlignment::Left => (0.0, 0.0),
+        TextAlignment::Centre => (spare_width / 2.0, 0.0),
+        TextAlignment::Right => (spare_width, 0.0),
+        TextAlignment::Justify if !ends_paragraph && spaces > 0 => (0.0, spare_width / spaces as f32),
+        TextAlignment::Justify => (0.0, 0.0),
+    }
+}
+
+/// Greedy word wrap, a word wider than `wrap_width` on its own is split between characters.
+fn break_lines(characters: &[markup::StyledChar], wrap_width: Option<f32>, width: &dyn Fn(usize, usize) -> f32) -> Vec<Line>
+{
+    let mut lines = Vec::new();
+    let mut paragraph_start = 0;
+
+    while paragraph_start <= characters.len()
+    {
+        let paragraph_end = characters[paragraph_start..].iter()
+            .position(|styled| styled.character == '\n')
+            .map_or(characters.len(), |offset| paragraph_start + offset);
+
+        let mut line_start = paragraph_start;
+        let mut line_end = paragraph_start;
+        let mut index = paragraph_start;
+
+        while index < paragraph_end
+        {
+            if characters[index].character.is_whitespace()
+            {
+                index += 1;
+                continue;
+            }
+
+            let word_end = characters[index..paragraph_end].iter()
+                .position(|styled| styled.character.is_whitespace())
+                .map_or(paragraph_end, |offset| index + offset);
+
+            if let Some(wrap_width) = wrap_width
+            {
+                if line_end > line_start && width(line_start, word_end) > wrap_width
+                {
+                    lines.push(Line { start: line_start, end: line_end, ends_paragraph: false });
+                    line_start = index;
+                    line_end = index;
+                }
+
+                if line_end == line_start && width(line_start, word_end) > wrap_width
+                {
+                    let mut split = line_start + 1;
+
+                    while split < word_end && width(line_start, split + 1) <= wrap_width {
+                        split += 1;
+                    }
+
+                    if split < word_end
+                    {
+                        lines.push(Line { start: line_start, end: split, ends_paragraph: false });
+                        line_start = split;
+                        line_end = split;
+                        index = split;
+                        continue;
+                    }
+                }
+            }
+
+            line_end = word_end;
+            index = word_end;
+        }
+
+        lines.push(Line { start: line_start, end: line_end, ends_paragraph: true });
+        paragraph_start = paragraph_end + 1;
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// Lines of `text` as strings, with every character one unit wide.
+    fn wrap(text: &str, wrap_width: Option<f32>) -> Vec<(String, bool)>
+    {
+        let characters = markup::parse(text);
+        let width = |start: usize, end: usize| (end - start) as f32;
+
+        break_lines(&characters, wrap_width, &width).iter()
+            .map(|line| (characters[line.start..line.end].iter().map(|styled| styled.character).collect(), line.ends_paragraph))
+            .collect()
+    }
+
+    fn line(text: &str, ends_paragraph: bool) -> (String, bool) {
+        (String::from(text), ends_paragraph)
+    }
+
+    #[test]
+    fn words_wrap_at_the_width()
+    {
+        assert_eq!(wrap("aaa bbb ccc", Some(7.0)), vec![line("aaa bbb", false), line("ccc", true)]);
+        assert_eq!(wrap("aaa bbb ccc", Some(6.0)), vec![line("aaa", false), line("bbb", false), line("ccc", true)]);
+    }
+
+    #[test]
+    fn without_a_width_only_newlines_break()
+    {
+        assert_eq!(wrap("aaa bbb ccc", None), vec![line("aaa bbb ccc", true)]);
+        assert_eq!(wrap("ab\ncd", None), vec![line("ab", true), line("cd", true)]);
+    }
+
+    #[test]
+    fn long_word_is_split_between_characters()
+    {
+        assert_eq!(wrap("abcdefghij", Some(4.0)), vec![line("abcd", false), line("efgh", false), line("ij", true)]);
+        assert_eq!(wrap("ab abcdefgh", Some(4.0)), vec![line("ab", false), line("abcd", false), line("efgh", true)]);
+    }
+
+    #[test]
+    fn markup_doesnt_take_up_width() {
+        assert_eq!(wrap("[colour=red]aaa[/colour] bbb", Some(7.0)), vec![line("aaa bbb", true)]);
+    }
+
+    #[test]
+    fn justify_stretches_spaces_on_all_but_the_last_line()
+    {
+        assert_eq!(align(TextAlignment::Justify, 6.0, 3, false), (0.0, 2.0));
+        assert_eq!(align(TextAlignment::Justify, 6.0, 3, true), (0.0, 0.0));
+        assert_eq!(align(TextAlignment::Justify, 6.0, 0, false), (0.0, 0.0));
+    }
+
+    #[test]
+    fn other_alignments_move_the_line()
+    {
+        assert_eq!(align(TextAlignment::Left, 6.0, 3, false), (0.0, 0.0));
+        assert_eq!(align(TextAlignment::Centre, 6.0, 3, false), (3.0, 0.0));
+        assert_eq!(align(TextAlignment::Right, 6.0, 3, false), (6.0, 0.0));
+    }
+
+    #[test]
+    fn wave_stays_smooth_late_in_a_game()
+    {
+        let late = 1_000_000.0;
+        let settings = TextSettings::new(8.0).time(late);
+        let step = TextSettings::new(8.0).time(late + 0.001);
+
+        assert!((settings.wave_offset(0) - step.wave_offset(0)).abs() < 0.1);
+    }
+}
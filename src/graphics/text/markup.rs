@@ -0,0 +1,120 @@
+use crate::graphics::colour::Colour;
+
+/// A character of marked up text together with the style the tags around it gave it.
+#[derive(Copy, Clone, Debug)]
+pub struct StyledChar
+{
+    pub character: char,
+    /// `None` uses the colour the text is drawn with.
+    pub colour: Option<Colour>,
+    pub wave: bool,
+}
+
+/// Strips the tags out of `text`. Supported tags are `[colour=red]` or `[colour=#ff8000]` closed
+/// by `[/colour]`, and `[wave]` closed by `[/wave]`, tags nest and `[[` writes a literal `[`.
+/// Anything else in brackets is kept as text.
+pub fn parse(text: &str) -> Vec<StyledChar>
+{
+    let mut styled = Vec::with_capacity(text.len());
+    let mut colours: Vec<Colour> = Vec::new();
+    let mut wave_depth = 0;
+    let mut rest = text;
+
+    while let Some(character) = rest.chars().next()
+    {
+        rest = &rest[character.len_utf8()..];
+
+        if character == '['
+        {
+            if let Some(escaped) = rest.strip_prefix('[')
+            {
+                rest = escaped;
+            }
+            else if let Some(end) = rest.find(']')
+            {
+                let tag = rest[..end].trim();
+                let mut is_tag = true;
+
+                match tag
+                {
+                    "/colour" | "/color" => { colours.pop(); }
+                    "wave" => wave_depth += 1,
+                    "/wave" => wave_depth = (wave_depth - 1).max(0),
+                    _ =>
+                    {
+                        let colour = tag.strip_prefix("colour=").or_else(|| tag.strip_prefix("color=")).and_then(Colour::from_name);
+
+                        match colour
+                        {
+                            Some(colour) => colours.push(colour),
+                            None => is_tag = false,
+                        }
+                    }
+                }
+
+                if is_tag
+                {
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+        }
+
+        styled.push(StyledChar { character, colour: colours.last().copied(), wave: wave_depth > 0 });
+    }
+
+    styled
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn text(styled: &[StyledChar]) -> String {
+        styled.iter().map(|styled| styled.character).collect()
+    }
+
+    #[test]
+    fn nested_tags_restore_the_outer_style()
+    {
+        let styled = parse("[colour=blue]a[colour=red]b[wave]c[/wave][/colour]d[/colour]e");
+
+        assert_eq!(text(&styled), "abcde");
+        assert_eq!(styled.iter().map(|styled| styled.colour).collect::<Vec<_>>(),
+                   vec![Some(Colour::BLUE), Some(Colour::RED), Some(Colour::RED), Some(Colour::BLUE), None]);
+        assert_eq!(styled.iter().map(|styled| styled.wave).collect::<Vec<_>>(), vec![false, false, true, false, false]);
+    }
+
+    #[test]
+    fn unclosed_tags_last_to_the_end()
+    {
+        let styled = parse("[colour=red][wave]ab");
+
+        assert_eq!(text(&styled), "ab");
+        assert!(styled.iter().all(|styled| styled.colour == Some(Colour::RED) && styled.wave));
+    }
+
+    #[test]
+    fn stray_closing_tags_are_ignored()
+    {
+        let styled = parse("[/wave][/colour]a");
+
+        assert_eq!(text(&styled), "a");
+        assert_eq!(styled[0].colour, None);
+        assert!(!styled[0].wave);
+    }
+
+    #[test]
+    fn double_bracket_is_a_literal_bracket() {
+        assert_eq!(text(&parse("[[colour=red]a")), "[colour=red]a");
+    }
+
+    #[test]
+    fn unknown_and_unterminated_tags_stay_text()
+    {
+        assert_eq!(text(&parse("[shout]a")), "[shout]a");
+        assert_eq!(text(&parse("[colour=nope]a")), "[colour=nope]a");
+        assert_eq!(text(&parse("a[wave")), "a[wave");
+    }
+}
@@ -0,0 +1,84 @@
+use crate::graphics::text::layout::TextLayout;
+
+/// Reveals laid out text one character at a time, pass `visible_characters` to `TextSettings`.
+pub struct Typewriter
+{
+    pub characters_per_second: f32,
+    elapsed: f32,
+    skipped: bool,
+}
+
+impl Typewriter
+{
+    pub fn new(characters_per_second: f32) -> Self {
+        Self { characters_per_second, elapsed: 0.0, skipped: false }
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.elapsed += delta_time;
+    }
+
+    pub fn visible_characters(&self) -> usize
+    {
+        if self.skipped {
+            return usize::MAX;
+        }
+
+        (self.elapsed * self.characters_per_second) as usize
+    }
+
+    /// Shows the rest of the text straight away.
+    pub fn skip(&mut self) {
+        self.skipped = true;
+    }
+
+    pub fn reset(&mut self)
+    {
+        self.elapsed = 0.0;
+        self.skipped = false;
+    }
+
+    pub fn is_finished(&self, layout: &TextLayout) -> bool {
+        self.visible_characters() >= layout.character_count
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::math::Vector2;
+
+    fn layout(character_count: usize) -> TextLayout {
+        TextLayout { glyphs: Vec::new(), size: Vector2::new(0.0, 0.0), font_size: 8.0, character_count }
+    }
+
+    #[test]
+    fn reveals_characters_as_time_passes()
+    {
+        let mut typewriter = Typewriter::new(8.0);
+        assert_eq!(typewriter.visible_characters(), 0);
+
+        typewriter.update(0.25);
+        assert_eq!(typewriter.visible_characters(), 2);
+
+        typewriter.update(0.2);
+        assert_eq!(typewriter.visible_characters(), 3);
+        assert!(!typewriter.is_finished(&layout(4)));
+
+        typewriter.update(0.1);
+        assert!(typewriter.is_finished(&layout(4)));
+    }
+
+    #[test]
+    fn skip_shows_everything_until_reset()
+    {
+        let mut typewriter = Typewriter::new(8.0);
+
+        typewriter.skip();
+        assert!(typewriter.is_finished(&layout(100)));
+
+        typewriter.reset();
+        assert_eq!(typewriter.visible_characters(), 0);
+    }
+}
@@ -3,9 +3,11 @@ use std::path::Path;
 use cgmath::Vector2; 
 use std::num::NonZeroU32;
 use image::{GenericImageView, ImageBuffer, Rgba};
+use crate::shapes::rectangle::Rectangle;
 use crate::platform::graphics_interface::GraphicsInterface;
 
 
+#[derive(Debug)]
 pub struct Texture
 {
     pub id: u64,
@@ -68,6 +70,47 @@ impl Texture
     }
 
 
+    /// Turns an area measured from the top of the texture, the way atlases and sprite sheet tools
+    /// give it, into the draw area `Draw::sprite_with` expects, which is measured from the bottom.
+    pub fn area_from_top(&self, position: Vector2<f32>, width: f32, height: f32) -> Rectangle {
+        Rectangle::new(Vector2::new(position.x, self.height as f32 - position.y - height), width, height)
+    }
+
+    /// A blank texture that can be rendered into, in the same format as the frame so every
+    /// pipeline can target it. Use it through `RenderTarget`.
+    pub fn new_render_target(graphics_interface: &GraphicsInterface, width: u32, height: u32) -> Self
+    {
+        let size = wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        };
+
+        let texture = graphics_interface.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("render target"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: graphics_interface.config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = graphics_interface.device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self { id: rand::random::<u64>(), width: size.width, height: size.height, texture, view, sampler }
+    }
+
+
     pub fn new_from_file(graphics_interface: &GraphicsInterface, file_path: &str) -> Self
     {
        
@@ -125,5 +168,24 @@ impl Texture
         Self { id: rand::random::<u64>(), width: dimensions.0, height: dimensions.1 , texture, view, sampler }
     }
 
+    /// Like `new_from_file`, but a file that can't be read or decoded is an error instead of a panic.
+    pub fn try_new_from_file(graphics_interface: &GraphicsInterface, file_path: &str) -> Result<Self, String>
+    {
+        let data = match fs::read(Path::new(file_path))
+        {
+            Ok(data) => data,
+            Err(error) => return Err(format!("Couldn't read texture {}: {}", file_path, error)),
+        };
+
+        let image = match image::load_from_memory(&data)
+        {
+            Ok(image) => image,
+            Err(error) => return Err(format!("Couldn't decode texture {}: {}", file_path, error)),
+        };
+
+        let (width, height) = image.dimensions();
+        Ok(Self::new_from_buffer(graphics_interface, image.to_rgba8(), Vector2::new(width, height)))
+    }
+
 
 }
\ No newline at end of file
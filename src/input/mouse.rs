@@ -6,6 +6,9 @@ pub use sdl2::mouse::MouseButton as MouseButton;
 pub struct Mouse
 {
     pub position: Vector2<f32>,
+    screen_position: Vector2<f32>,
+    offset: Vector2<f32>,
+    scale: Vector2<f32>,
     buttons_down: HashSet<MouseButton>,
     buttons_pressed: HashSet<MouseButton>,
     buttons_released: HashSet<MouseButton>,
@@ -20,7 +23,10 @@ impl Mouse
             buttons_down: HashSet::new(), 
             buttons_pressed: HashSet::new(), 
             buttons_released: HashSet::new(),
-            position: Vector2 { x: 0.0, y:  0.0 }
+            position: Vector2 { x: 0.0, y:  0.0 },
+            screen_position: Vector2 { x: 0.0, y:  0.0 },
+            offset: Vector2 { x: 0.0, y:  0.0 },
+            scale: Vector2 { x: 1.0, y:  1.0 },
         }
     }
 
@@ -44,14 +50,31 @@ impl Mouse
                     self.buttons_released.insert(*mouse_btn);
                 }
             }
-            Event::MouseMotion { x, y, .. } => {
-                self.position = Vector2::new(*x as f32, *y as f32);
+            Event::MouseMotion { x, y, .. } => 
+            {
+                self.screen_position = Vector2::new(*x as f32, *y as f32);
+                self.update_position();
             }
 
             _ => {}
         }
     }
 
+    /// Maps window positions into another space as `(position - offset) / scale`, 
+    /// used to report the mouse in virtual resolution pixels.
+    pub fn set_transform(&mut self, offset: Vector2<f32>, scale: Vector2<f32>)
+    {
+        self.offset = offset;
+        self.scale = scale;
+        self.update_position();
+    }
+
+    fn update_position(&mut self)
+    {
+        self.position = Vector2::new((self.screen_position.x - self.offset.x) / self.scale.x, 
+                                     (self.screen_position.y - self.offset.y) / self.scale.y);
+    }
+
     pub fn clear(&mut self)
     {
         self.buttons_pressed.clear();
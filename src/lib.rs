@@ -1,5 +1,6 @@
 pub mod math;
 pub mod game;
+pub mod game_time;
 pub mod input;
 pub mod sound;
 pub mod shapes;
@@ -8,4 +9,5 @@ pub mod platform;
 pub mod graphics;
 pub mod collision;
 pub mod animation;
-pub use crate::game::Game;
\ No newline at end of file
+pub use crate::game::Game;
+pub use crate::game_time::GameTime;
\ No newline at end of file
@@ -1,2 +1,6 @@
 pub mod system_sdl;
-pub mod graphics_interface;
\ No newline at end of file
+pub mod graphics_interface;
+pub mod virtual_resolution;
+pub mod gpu_buffer;
+pub mod post_process;
+pub mod screenshot;
\ No newline at end of file
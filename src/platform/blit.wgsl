@@ -0,0 +1,29 @@
+struct VertexOutput 
+{
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+// One triangle that covers the whole viewport, the corners past the edges get clipped.
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput 
+{
+    var out: VertexOutput;
+    let x = f32((vertex_index << 1u) & 2u);
+    let y = f32(vertex_index & 2u);
+
+    out.tex_coords = vec2<f32>(x, y);
+    out.clip_position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    return out;
+}
+
+@group(0) @binding(0)
+var source_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) ->  @location(0) vec4<f32> 
+{
+    return textureSample(source_texture, source_sampler, in.tex_coords);
+}
@@ -0,0 +1,61 @@
+/// A GPU buffer that is written with `queue.write_buffer` instead of being recreated every draw.
+///
+/// Writes are placed one after another until `reset`, so every batch of a frame keeps its own
+/// region, and the buffer is replaced by one twice the size whenever a write doesn't fit.
+pub struct GpuBuffer
+{
+    label: &'static str,
+    usage: wgpu::BufferUsages,
+    capacity: wgpu::BufferAddress,
+    cursor: wgpu::BufferAddress,
+    pub buffer: wgpu::Buffer,
+}
+
+impl GpuBuffer
+{
+    pub fn new(device: &wgpu::Device, label: &'static str, usage: wgpu::BufferUsages, capacity: wgpu::BufferAddress) -> Self
+    {
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        let buffer = Self::create_buffer(device, label, usage, capacity);
+
+        Self { label, usage, capacity, cursor: 0, buffer }
+    }
+
+    /// Copies `data` into the buffer and returns the byte range it was written to.
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[u8]) -> std::ops::Range<wgpu::BufferAddress>
+    {
+        let size = data.len() as wgpu::BufferAddress;
+        let aligned_size = wgpu::util::align_to(size, wgpu::COPY_BUFFER_ALIGNMENT);
+
+        if self.cursor + aligned_size > self.capacity
+        {
+            self.capacity = (self.capacity * 2).max((self.cursor + aligned_size).next_power_of_two());
+            self.buffer = Self::create_buffer(device, self.label, self.usage, self.capacity);
+            self.cursor = 0;
+        }
+
+        let start = self.cursor;
+
+        if size > 0 {
+            queue.write_buffer(&self.buffer, start, data);
+        }
+
+        self.cursor += aligned_size;
+        start..start + size
+    }
+
+    /// Lets the next writes start from the beginning of the buffer again.
+    pub fn reset(&mut self) {
+        self.cursor = 0;
+    }
+
+    fn create_buffer(device: &wgpu::Device, label: &'static str, usage: wgpu::BufferUsages, capacity: wgpu::BufferAddress) -> wgpu::Buffer
+    {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: capacity,
+            usage,
+            mapped_at_creation: false,
+        })
+    }
+}
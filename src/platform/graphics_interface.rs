@@ -1,12 +1,23 @@
-use log::warn;
+use log::{info, warn};
 use wgpu::CommandEncoder;
 use crate::math;
 use cgmath::Matrix4;
 use std::num::NonZeroU32;
-use std::{iter, sync::Arc};
+use std::{iter, ops::Range, sync::{mpsc, Arc, Mutex}, collections::HashMap, path::{Path, PathBuf}};
+use image::RgbaImage;
 use crate::graphics::texture::Texture;
+use crate::graphics::blend_mode::BlendMode;
+use crate::graphics::material::Material;
 use crate::platform::system_sdl::SDLSystem;
-use wgpu::{util::DeviceExt, Sampler, TextureView};
+use crate::window::settings::WindowSettings;
+use crate::shapes::rectangle::Rectangle;
+use crate::math::Vector2;
+use crate::platform::virtual_resolution::{VirtualResolution, ScalingMode};
+use crate::platform::gpu_buffer::GpuBuffer;
+use crate::platform::post_process::PostProcess;
+use crate::platform::screenshot::{CaptureTarget, PendingScreenshot};
+use crate::graphics::post_effect::PostEffect;
+use wgpu::{Sampler, TextureView};
 
 
 
@@ -29,67 +40,116 @@ pub struct  ShapeVertex
     pub color: [f32; 4],
 }
 
+/// Number of textures a sprite draw can bind at once when the adapter supports texture binding arrays.
+pub const MAX_BATCH_TEXTURES: u32 = 16;
+
+/// A run of draws in a batch that share a pipeline, ranges index into the batch's index lists.
+pub enum BatchCommand
+{
+    Sprites { indices: Range<u32>, textures: Vec<Arc<Texture>>, blend_mode: BlendMode },
+    Shapes { indices: Range<u32>, blend_mode: BlendMode },
+}
+
+/// Everything `Draw::end` hands over to be rendered.
+pub struct BatchData<'a>
+{
+    pub sprite_vertices: &'a [SpriteVertex],
+    pub sprite_indices: &'a [u32],
+    pub shape_vertices: &'a [ShapeVertex],
+    pub shape_indices: &'a [u32],
+    pub commands: &'a [BatchCommand],
+    /// Replaces the built in sprite shader for every sprite in the batch.
+    pub material: Option<&'a Material>,
+    pub viewport: Option<Rectangle>,
+    pub scissor: Option<Rectangle>,
+    /// Drawn into instead of the frame when set.
+    pub render_target: Option<&'a Texture>,
+}
+
+/// Frames a texture bind group can go unused before it is dropped from the cache.
+const BIND_GROUP_CACHE_FRAMES: u64 = 120;
+
+struct CachedBindGroup
+{
+    bind_group: wgpu::BindGroup,
+    last_used_frame: u64,
+}
+
 pub struct GraphicsInterface
 {
     pub queue: wgpu::Queue,
     pub device: wgpu::Device,
-    pub surface: wgpu::Surface,
+    /// `None` when headless, frames are drawn into `offscreen_target` instead.
+    pub surface: Option<wgpu::Surface>,
+    offscreen_target: Option<wgpu::Texture>,
     pub world_matrix: Matrix4<f32>,
     pub config: wgpu::SurfaceConfiguration,
+    max_batch_textures: u32,
     texture_bind_group_layout: wgpu::BindGroupLayout,
-    sprite_render_pipeline: wgpu::RenderPipeline,
-    rectangle_render_pipeline: wgpu::RenderPipeline,
-    circle_render_pipeline: wgpu::RenderPipeline,
+    material_bind_group_layout: wgpu::BindGroupLayout,
+    sprite_render_pipelines: Vec<wgpu::RenderPipeline>,
+    shape_render_pipelines: Vec<wgpu::RenderPipeline>,
+    blit_bind_group_layout: wgpu::BindGroupLayout,
+    blit_render_pipeline: wgpu::RenderPipeline,
+    blit_sampler: wgpu::Sampler,
+    virtual_resolution: Option<VirtualResolution>,
+    post_process: PostProcess,
+    frame: Option<Frame>,
+    /// `begin_frame` couldn't get a surface texture, so this frame's batches are dropped without a warning.
+    is_frame_skipped: bool,
+    frame_count: u64,
+    /// Directories a screenshot of the next presented frame is saved to.
+    screenshot_requests: Vec<PathBuf>,
+    pending_screenshots: Vec<PendingScreenshot>,
+    sprite_vertex_buffer: GpuBuffer,
+    sprite_index_buffer: GpuBuffer,
+    shape_vertex_buffer: GpuBuffer,
+    shape_index_buffer: GpuBuffer,
+    texture_bind_groups: HashMap<Vec<u64>, CachedBindGroup>,
     clear_color: wgpu::Color,
 }
 
-impl GraphicsInterface
+struct Frame
 {
-    pub fn new(sdl2_system: &SDLSystem) -> Result<Self, String>
-    {
-        let instance = wgpu::Instance::new(wgpu::Backends::DX12);
-        let surface = unsafe { instance.create_surface(&sdl2_system.window) };
-
-        let (width, height) = sdl2_system.window.size();
+    /// `None` when headless.
+    output: Option<wgpu::SurfaceTexture>,
+    view: wgpu::TextureView,
+    /// Drawn into in place of `view` when a screenshot is taken, then copied over at present.
+    capture: Option<CaptureTarget>,
+    is_cleared: bool,
+    /// Batches draw into the post process scene instead of the real target this frame.
+    is_post_processed: bool,
+}
 
-        let adapter_opt = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions 
-        {
-            power_preference: wgpu::PowerPreference::default(),
-            compatible_surface: Some(&surface),
-            force_fallback_adapter: false,
-        }));
-        
-        let adapter = match adapter_opt 
+impl Frame
+{
+    /// Where the frame is drawn before it is presented.
+    fn draw_view(&self) -> &TextureView
+    {
+        match &self.capture
         {
-            Some(a) => a,
-            None => return Err(String::from("No adapter found")),
-        };
-
-        let adapter_features = adapter.features();
-
-        if ! adapter_features.contains(wgpu::Features::TEXTURE_BINDING_ARRAY) {
-            return Err(String::from("Texture Binding isn't supported !"));
+            Some(capture) => &capture.view,
+            None => &self.view,
         }
+    }
+}
 
-        if !adapter_features.contains(wgpu::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING) {
-            return Err(String::from("Sampled Texture and Storage Buffer Array Non Uniform Indexing isn't supported !"));
-        }
+impl GraphicsInterface
+{
+    pub fn new(sdl2_system: &SDLSystem, settings: &WindowSettings) -> Result<Self, String>
+    {
+        let (surface, adapter) = Self::request_adapter(sdl2_system, settings)?;
+        let (device, queue, max_batch_textures) = Self::request_device(&adapter)?;
 
-        if ! adapter_features.contains(wgpu::Features::POLYGON_MODE_LINE) {
-            return Err(String::from("Polygon mode line isn't supported !"));
-        }
+        let (width, height) = sdl2_system.window.size();
 
-        let (device, queue) = match pollster::block_on(adapter.request_device(
-            &wgpu::DeviceDescriptor 
-            {
-                label: Some("device"),
-                limits: wgpu::Limits::default(),
-                features:  wgpu::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING | wgpu::Features::TEXTURE_BINDING_ARRAY | wgpu::Features::POLYGON_MODE_LINE
-            },
-            None,
-        )) {
-            Ok(a) => a,
-            Err(e) => return Err(e.to_string()),
+        let present_mode = if surface.get_supported_modes(&adapter).contains(&settings.present_mode) {
+            settings.present_mode
+        }
+        else 
+        {
+            warn!("Present mode {:?} isn't supported, falling back to Fifo", settings.present_mode);
+            wgpu::PresentMode::Fifo
         };
 
         let config = wgpu::SurfaceConfiguration 
@@ -98,14 +158,47 @@ impl GraphicsInterface
             format: surface.get_supported_formats(&adapter)[0],
             width,
             height,
-            present_mode: wgpu::PresentMode::Immediate,
+            present_mode,
         };
 
         surface.configure(&device, &config);
-            
+
+        Ok(Self::with_device(Some(surface), device, queue, config, max_batch_textures))
+    }
+
+    /// Renders into an offscreen texture instead of a window, for tests and tools on machines
+    /// without a display. Read what was drawn back with `read_frame` after `present_frame`.
+    /// Falls back to a software adapter, like lavapipe or WARP, when there's no GPU.
+    pub fn new_headless(width: u32, height: u32) -> Result<Self, String>
+    {
+        let adapter = Self::request_headless_adapter()?;
+        let (device, queue, max_batch_textures) = Self::request_device(&adapter)?;
+
+        let config = wgpu::SurfaceConfiguration 
+        {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            width: width.max(1),
+            height: height.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+
+        Ok(Self::with_device(None, device, queue, config, max_batch_textures))
+    }
+
+    fn with_device(surface: Option<wgpu::Surface>, device: wgpu::Device, queue: wgpu::Queue, config: wgpu::SurfaceConfiguration, max_batch_textures: u32) -> Self
+    {
+        let (width, height) = (config.width, config.height);
+
+        let offscreen_target = match &surface
+        {
+            Some(_) => None,
+            None => Some(Self::create_offscreen_target(&device, &config)),
+        };
+
         let sprite_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Sprite Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("sprite.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(format!("{}\n{}", Self::texture_bindings_source(max_batch_textures), include_str!("sprite.wgsl")).into()),
         });
         
         let texture_bind_group_layout =
@@ -119,19 +212,36 @@ impl GraphicsInterface
                             view_dimension: wgpu::TextureViewDimension::D2,
                             sample_type: wgpu::TextureSampleType::Float { filterable: true },
                         },
-                        count: NonZeroU32::new(16),
+                        count: Self::texture_binding_count(max_batch_textures),
                     },
                     wgpu::BindGroupLayoutEntry {
                         binding: 1,
                         visibility: wgpu::ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: NonZeroU32::new(16),
+                        count: Self::texture_binding_count(max_batch_textures),
                     },
                 ],
             label: Some("texture_bind_group_layout"),
         });
        
 
+        let material_bind_group_layout =
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            label: Some("material_bind_group_layout"),
+        });
+
         let sprite_render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Sprite Render Pipeline Layout"),
@@ -139,24 +249,75 @@ impl GraphicsInterface
                 push_constant_ranges: &[],
         });
 
-        let sprite_render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Sprite Render Pipeline"),
-            layout: Some(&sprite_render_pipeline_layout),
+        let sprite_render_pipelines = BlendMode::ALL.iter()
+            .map(|blend_mode| Self::create_sprite_pipeline(&device, &sprite_render_pipeline_layout, &sprite_shader, config.format, *blend_mode))
+            .collect();
+
+        let shape_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shape Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shape.wgsl").into()),
+        });
+
+        let shape_render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Shape Render Pipeline Layout"),
+                bind_group_layouts: &[],
+                push_constant_ranges: &[],
+        });
+
+        let shape_render_pipelines = BlendMode::ALL.iter()
+            .map(|blend_mode| Self::create_shape_pipeline(&device, &shape_render_pipeline_layout, &shape_shader, config.format, *blend_mode))
+            .collect();
+
+        let blit_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Blit Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("blit.wgsl").into()),
+        });
+
+        let blit_bind_group_layout =
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            label: Some("blit_bind_group_layout"),
+        });
+
+        let blit_render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Blit Render Pipeline Layout"),
+                bind_group_layouts: &[&blit_bind_group_layout],
+                push_constant_ranges: &[],
+        });
+
+        let blit_render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Blit Render Pipeline"),
+            layout: Some(&blit_render_pipeline_layout),
             vertex: wgpu::VertexState {
-                module: &sprite_shader,
+                module: &blit_shader,
                 entry_point: "vs_main",
-                buffers: &[wgpu::VertexBufferLayout {
-                    array_stride: std::mem::size_of::<SpriteVertex>() as wgpu::BufferAddress,
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &wgpu::vertex_attr_array![0 => Sint32, 1 => Float32x2, 2 => Float32x2, 3 => Float32x4],
-                }],
+                buffers: &[],
             },
             fragment: Some(wgpu::FragmentState {
-                module: &sprite_shader,
+                module: &blit_shader,
                 entry_point: "fs_main",
                 targets: &[Some(wgpu::ColorTargetState {
                     format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    blend: None,
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
             }),
@@ -164,7 +325,7 @@ impl GraphicsInterface
                 topology: wgpu::PrimitiveTopology::TriangleList,
                 strip_index_format: None,
                 front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
+                cull_mode: None,
                 polygon_mode: wgpu::PolygonMode::Fill,
                 unclipped_depth: false,
                 conservative: false,
@@ -178,45 +339,255 @@ impl GraphicsInterface
             multiview: None,
         });
 
-        let shape_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Rectangle Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shape.wgsl").into()),
+        let blit_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
         });
 
-        let rectangle_render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Rectangle Render Pipeline Layout"),
-                bind_group_layouts: &[],
-                push_constant_ranges: &[],
+        let sprite_vertex_buffer = GpuBuffer::new(&device, "Sprite Vertex Buffer", wgpu::BufferUsages::VERTEX, 1 << 16);
+        let sprite_index_buffer = GpuBuffer::new(&device, "Sprite Index Buffer", wgpu::BufferUsages::INDEX, 1 << 16);
+        let shape_vertex_buffer = GpuBuffer::new(&device, "Shape Vertex Buffer", wgpu::BufferUsages::VERTEX, 1 << 16);
+        let shape_index_buffer = GpuBuffer::new(&device, "Shape Index Buffer", wgpu::BufferUsages::INDEX, 1 << 16);
+
+        let post_process = PostProcess::new(&device, config.format);
+
+        let clear_color = wgpu::Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 };
+        let world_matrix = math::ortho(0.0, width as f32, height as f32, 0.0, -1.0, 1.0);
+        
+        Self{ surface, offscreen_target, device, queue, config, max_batch_textures, texture_bind_group_layout, material_bind_group_layout, sprite_render_pipelines, shape_render_pipelines, 
+              blit_bind_group_layout, blit_render_pipeline, blit_sampler, virtual_resolution: None, post_process, frame: None, is_frame_skipped: false, frame_count: 0, 
+              screenshot_requests: Vec::new(), pending_screenshots: Vec::new(), 
+              sprite_vertex_buffer, sprite_index_buffer, shape_vertex_buffer, shape_index_buffer, texture_bind_groups: HashMap::new(), 
+              clear_color, world_matrix }
+    }
+
+    /// Tries each backend in turn, the `WGPU_BACKEND` environment variable (for example `vulkan`, `gl`
+    /// or `dx12`) comes first, then `WindowSettings::backends`, then the primary backends
+    /// (Vulkan, Metal, DX12) followed by the secondary ones (GL, DX11), so a chosen backend that isn't
+    /// available still falls back. `WGPU_ADAPTER_NAME` and `WGPU_POWER_PREF` can pick a specific
+    /// adapter, like lavapipe on machines without a GPU.
+    fn request_adapter(sdl2_system: &SDLSystem, settings: &WindowSettings) -> Result<(wgpu::Surface, wgpu::Adapter), String>
+    {
+        let preferences = [wgpu::util::backend_bits_from_env(), settings.backends, Some(wgpu::Backends::PRIMARY), Some(wgpu::Backends::SECONDARY)];
+        let mut candidates: Vec<wgpu::Backends> = Vec::with_capacity(preferences.len());
+
+        for backends in preferences.into_iter().flatten()
+        {
+            if !backends.is_empty() && !candidates.contains(&backends) {
+                candidates.push(backends);
+            }
+        }
+
+        let power_preference = wgpu::util::power_preference_from_env().unwrap_or_default();
+
+        for backends in candidates
+        {
+            let instance = wgpu::Instance::new(backends);
+            let surface = unsafe { instance.create_surface(&sdl2_system.window) };
+
+            let adapter = match wgpu::util::initialize_adapter_from_env(&instance, backends)
+            {
+                Some(adapter) if adapter.is_surface_supported(&surface) => Some(adapter),
+                _ => pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions 
+                {
+                    power_preference,
+                    compatible_surface: Some(&surface),
+                    force_fallback_adapter: false,
+                })),
+            };
+
+            let adapter = match adapter
+            {
+                Some(adapter) => adapter,
+                None => 
+                {
+                    warn!("No adapter found for backends {:?}", backends);
+                    continue;
+                }
+            };
+
+            let adapter_info = adapter.get_info();
+            info!("Using adapter {} ({:?}, {:?})", adapter_info.name, adapter_info.backend, adapter_info.device_type);
+            return Ok((surface, adapter));
+        }
+
+        Err(String::from("No suitable adapter found on any backend"))
+    }
+
+    /// Like `request_adapter` without a surface to be compatible with, trying a software adapter
+    /// if no hardware one is found.
+    fn request_headless_adapter() -> Result<wgpu::Adapter, String>
+    {
+        let backends = wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::all());
+        let power_preference = wgpu::util::power_preference_from_env().unwrap_or_default();
+        let instance = wgpu::Instance::new(backends);
+
+        let mut adapter = wgpu::util::initialize_adapter_from_env(&instance, backends);
+
+        for force_fallback_adapter in [false, true]
+        {
+            if adapter.is_some() {
+                break;
+            }
+
+            adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions 
+            {
+                power_preference,
+                compatible_surface: None,
+                force_fallback_adapter,
+            }));
+        }
+
+        match adapter
+        {
+            Some(adapter) => 
+            {
+                let adapter_info = adapter.get_info();
+                info!("Using headless adapter {} ({:?}, {:?})", adapter_info.name, adapter_info.backend, adapter_info.device_type);
+                Ok(adapter)
+            }
+            None => Err(format!("No suitable headless adapter found for backends {:?}", backends)),
+        }
+    }
+
+    /// Opens the device, along with the number of textures a sprite command can bind on it.
+    fn request_device(adapter: &wgpu::Adapter) -> Result<(wgpu::Device, wgpu::Queue, u32), String>
+    {
+        // Without binding arrays sprites fall back to one texture per draw call, which GL, older
+        // and integrated GPUs and software adapters can all handle.
+        let texture_array_features = wgpu::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING | wgpu::Features::TEXTURE_BINDING_ARRAY;
+        let supports_texture_arrays = adapter.features().contains(texture_array_features);
+
+        let (required_features, limits, max_batch_textures) = if supports_texture_arrays {
+            (texture_array_features, wgpu::Limits::default(), MAX_BATCH_TEXTURES)
+        }
+        else 
+        {
+            info!("Adapter doesn't support texture binding arrays, sprites will bind one texture per draw call");
+            (wgpu::Features::empty(), wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits()), 1)
+        };
+
+        match pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor 
+            {
+                label: Some("device"),
+                limits,
+                features: required_features,
+            },
+            None,
+        )) {
+            Ok((device, queue)) => Ok((device, queue, max_batch_textures)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn create_offscreen_target(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::Texture
+    {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("offscreen target"),
+            size: wgpu::Extent3d { width: config.width, height: config.height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: config.usage,
+        })
+    }
+
+    /// Whether this renders to an offscreen texture instead of a window.
+    pub fn is_headless(&self) -> bool {
+        self.surface.is_none()
+    }
+
+    /// Number of textures a sprite command can bind, `MAX_BATCH_TEXTURES` or 1 on downlevel adapters.
+    pub fn max_batch_textures(&self) -> u32 {
+        self.max_batch_textures
+    }
+
+    /// WGSL declaring the sprite textures and the `sample_sprite` function the sprite shaders use.
+    fn texture_bindings_source(max_batch_textures: u32) -> &'static str
+    {
+        if max_batch_textures > 1 {
+            include_str!("texture_array.wgsl")
+        }
+        else {
+            include_str!("texture_single.wgsl")
+        }
+    }
+
+    fn texture_binding_count(max_batch_textures: u32) -> Option<NonZeroU32>
+    {
+        if max_batch_textures > 1 {
+            NonZeroU32::new(max_batch_textures)
+        }
+        else {
+            None
+        }
+    }
+
+    /// Layout of the uniform buffer a material binds at `@group(1)`.
+    pub fn material_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.material_bind_group_layout
+    }
+
+    /// Compiles a material's fragment shader between the material prelude and entry points,
+    /// with a pipeline for every blend mode in `BlendMode::ALL` order.
+    pub fn create_material_pipelines(&self, fragment_source: &str) -> Vec<wgpu::RenderPipeline>
+    {
+        let source = format!("{}\n{}\n{}\n{}", Self::texture_bindings_source(self.max_batch_textures), include_str!("material_prelude.wgsl"), 
+                             fragment_source, include_str!("material_entry.wgsl"));
+
+        let shader = self.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Material Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        let layout = self.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Material Render Pipeline Layout"),
+            bind_group_layouts: &[&self.texture_bind_group_layout, &self.material_bind_group_layout],
+            push_constant_ranges: &[],
         });
 
-        let rectangle_render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Rectangle Render Pipeline"),
-            layout: Some(&rectangle_render_pipeline_layout),
+        BlendMode::ALL.iter()
+            .map(|blend_mode| Self::create_sprite_pipeline(&self.device, &layout, &shader, self.config.format, *blend_mode))
+            .collect()
+    }
+
+    fn create_sprite_pipeline(device: &wgpu::Device, layout: &wgpu::PipelineLayout, shader: &wgpu::ShaderModule, format: wgpu::TextureFormat, blend_mode: BlendMode) -> wgpu::RenderPipeline
+    {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Sprite Render Pipeline"),
+            layout: Some(layout),
             vertex: wgpu::VertexState {
-                module: &shape_shader,
+                module: shader,
                 entry_point: "vs_main",
                 buffers: &[wgpu::VertexBufferLayout {
-                    array_stride: std::mem::size_of::<ShapeVertex>() as wgpu::BufferAddress,
+                    array_stride: std::mem::size_of::<SpriteVertex>() as wgpu::BufferAddress,
                     step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4],
+                    attributes: &wgpu::vertex_attr_array![0 => Sint32, 1 => Float32x2, 2 => Float32x2, 3 => Float32x4],
                 }],
             },
             fragment: Some(wgpu::FragmentState {
-                module: &shape_shader,
-                entry_point: "fs_main",
+                module: shader,
+                entry_point: blend_mode.fragment_entry_point(),
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    format,
+                    blend: Some(blend_mode.blend_state()),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
             }),
             primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::LineList,
+                topology: wgpu::PrimitiveTopology::TriangleList,
                 strip_index_format: None,
                 front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Line,
+                // Sprites mirrored by a negative scale wind the other way and still have to be drawn.
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
                 unclipped_depth: false,
                 conservative: false,
             },
@@ -227,20 +598,16 @@ impl GraphicsInterface
                 alpha_to_coverage_enabled: false,
             },
             multiview: None,
-        });
-
-        let circle_render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Circle Render Pipeline Layout"),
-                bind_group_layouts: &[],
-                push_constant_ranges: &[],
-        });
+        })
+    }
 
-        let circle_render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Circle Render Pipeline"),
-            layout: Some(&circle_render_pipeline_layout),
+    fn create_shape_pipeline(device: &wgpu::Device, layout: &wgpu::PipelineLayout, shader: &wgpu::ShaderModule, format: wgpu::TextureFormat, blend_mode: BlendMode) -> wgpu::RenderPipeline
+    {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shape Render Pipeline"),
+            layout: Some(layout),
             vertex: wgpu::VertexState {
-                module: &shape_shader,
+                module: shader,
                 entry_point: "vs_main",
                 buffers: &[wgpu::VertexBufferLayout {
                     array_stride: std::mem::size_of::<ShapeVertex>() as wgpu::BufferAddress,
@@ -249,20 +616,20 @@ impl GraphicsInterface
                 }],
             },
             fragment: Some(wgpu::FragmentState {
-                module: &shape_shader,
-                entry_point: "fs_main",
+                module: shader,
+                entry_point: blend_mode.fragment_entry_point(),
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    format,
+                    blend: Some(blend_mode.blend_state()),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
             }),
             primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::LineList,
+                topology: wgpu::PrimitiveTopology::TriangleList,
                 strip_index_format: None,
                 front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Line,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
                 unclipped_depth: false,
                 conservative: false,
             },
@@ -273,159 +640,405 @@ impl GraphicsInterface
                 alpha_to_coverage_enabled: false,
             },
             multiview: None,
-        });
+        })
+    }
 
-        let clear_color = wgpu::Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 };
-        let world_matrix = math::ortho(0.0, width as f32, height as f32, 0.0, -1.0, 1.0);
-        
-        Ok(Self{ surface, device, queue, config, texture_bind_group_layout, sprite_render_pipeline, rectangle_render_pipeline, circle_render_pipeline, clear_color, world_matrix })
+    /// Renders everything into a `width` by `height` target that gets scaled up to the window
+    /// with nearest filtering, the world matrix and mouse position are in virtual pixels from then on.
+    pub fn set_virtual_resolution(&mut self, width: u32, height: u32, scaling_mode: ScalingMode)
+    {
+        self.virtual_resolution = Some(VirtualResolution::new(&self.device, self.config.format, &self.blit_bind_group_layout, 
+                                                              &self.blit_sampler, width, height, scaling_mode));
+        self.update_world_matrix();
     }
 
-    pub fn clear(&mut self, red : f64, green: f64, blue: f64, alpha: f64) {
-        self.clear_color = wgpu::Color { r: red, g: green, b: blue, a: alpha };
+    pub fn clear_virtual_resolution(&mut self)
+    {
+        self.virtual_resolution = None;
+        self.update_world_matrix();
     }
 
-    pub fn batch_render(& mut self, textures: &Vec<Arc<Texture>>, vertices: &Vec<SpriteVertex>, indices: &Vec<u16>, 
-                        rectangle_vertices: &Vec<ShapeVertex>, rectangle_indices: &Vec<u16>,
-                        circle_vertices: &Vec<ShapeVertex>, circle_indices: &Vec<u16>)
-    { 
-        match self.internal_batch_render(textures, vertices, indices, 
-                                         rectangle_vertices, rectangle_indices, 
-                                         circle_vertices, circle_indices) 
+    /// The area of the window the frame ends up in, the whole window unless a virtual resolution is set.
+    pub fn virtual_viewport(&self) -> Rectangle
+    {
+        match &self.virtual_resolution
         {
-            Ok(_) => {}
-            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => self.surface.configure(&self.device, &self.config),
-            Err(wgpu::SurfaceError::OutOfMemory) => panic!("System has run out of memory"),
-            Err(wgpu::SurfaceError::Timeout) => warn!("Surface timeout"),
+            Some(virtual_resolution) => virtual_resolution.viewport(self.config.width, self.config.height),
+            None => Rectangle::new(Vector2::new(0.0, 0.0), self.config.width as f32, self.config.height as f32),
         }
     }
 
-    fn internal_batch_render(& mut self, textures: &Vec<Arc<Texture>>,  sprite_vertices: &Vec<SpriteVertex>, sprite_indices: &Vec<u16>, 
-                              rectangle_vertices: &Vec<ShapeVertex>, rectangle_indices: &Vec<u16>,
-                              circle_vertices: &Vec<ShapeVertex>, circle_indices: &Vec<u16>) -> Result<(), wgpu::SurfaceError>
+    /// Offset and scale that take a window position to a virtual one, `(position - offset) / scale`.
+    pub fn virtual_transform(&self) -> (Vector2<f32>, Vector2<f32>)
     {
-        let output = self.surface.get_current_texture()?;
-        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let viewport = self.virtual_viewport();
 
-        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("Render Encoder"),
-        });
+        match &self.virtual_resolution
+        {
+            Some(virtual_resolution) => 
+            {
+                let scale = Vector2::new(viewport.width / virtual_resolution.width as f32, viewport.height / virtual_resolution.height as f32);
+                (viewport.position, scale)
+            }
+            None => (Vector2::new(0.0, 0.0), Vector2::new(1.0, 1.0)),
+        }
+    }
 
-        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Render Pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(self.clear_color),
-                    store: true,
-                },
-            })],
-            depth_stencil_attachment: None,
-        });
+    pub fn screen_to_virtual(&self, position: Vector2<f32>) -> Vector2<f32>
+    {
+        let (offset, scale) = self.virtual_transform();
+        Vector2::new((position.x - offset.x) / scale.x, (position.y - offset.y) / scale.y)
+    }
 
-        if sprite_vertices.len() > 0 {
-            self.sprite_renderpass(&view, &mut encoder, textures, sprite_vertices, sprite_indices);
+    /// Size in pixels of what batches draw into, the virtual resolution when there is one.
+    pub fn target_size(&self) -> (u32, u32)
+    {
+        match &self.virtual_resolution
+        {
+            Some(virtual_resolution) => (virtual_resolution.width, virtual_resolution.height),
+            None => (self.config.width, self.config.height),
         }
+    }
 
-        if rectangle_vertices.len() > 0 {
-            self.rectangle_renderpass(&view, &mut encoder, rectangle_vertices, rectangle_indices);
+    /// Takes pixels to clip space for a batch drawn into `viewport`, or into the whole target when it is `None`.
+    pub fn projection_matrix(&self, viewport: Option<&Rectangle>) -> Matrix4<f32>
+    {
+        match viewport
+        {
+            Some(viewport) => math::ortho(0.0, viewport.width, viewport.height, 0.0, -1.0, 1.0),
+            None => self.world_matrix,
         }
+    }
+
+    fn update_world_matrix(&mut self)
+    {
+        let (width, height) = self.target_size();
+
+        self.world_matrix = math::ortho(0.0, width as f32, height as f32, 0.0, -1.0, 1.0);
+    }
 
-        if circle_vertices.len() > 0  {
-            self.circle_renderpass(&view, &mut encoder, circle_vertices, circle_indices);
+    pub fn resize(&mut self, width: u32, height: u32)
+    {
+        if width == 0 || height == 0 {
+            return;
         }
 
-        self.queue.submit(iter::once(encoder.finish()));  
-        output.present(); 
+        self.config.width = width;
+        self.config.height = height;
+
+        match &self.surface
+        {
+            Some(surface) => surface.configure(&self.device, &self.config),
+            None => self.offscreen_target = Some(Self::create_offscreen_target(&self.device, &self.config)),
+        }
 
-        Ok(())
+        self.update_world_matrix();
     }
 
+    /// Adds a full screen pass to the end of the post effect chain, it runs from the next frame on.
+    /// Cleared effects keep running until the frame in progress is presented.
+    pub fn add_post_effect(&mut self, effect: Arc<Mutex<dyn PostEffect>>) {
+        self.post_process.add_effect(&self.device, effect);
+    }
 
-    fn sprite_renderpass(&mut self, view: &TextureView, encoder: &mut CommandEncoder,  textures: &Vec<Arc<Texture>>,  vertices: &Vec<SpriteVertex>, indices: &Vec<u16>)
-    {
-        let mut texture_view_vec: Vec<&TextureView> = Vec::with_capacity(textures.len());
-        let mut texture_sampler_vec: Vec<&Sampler> = Vec::with_capacity(textures.len());
+    pub fn clear_post_effects(&mut self) {
+        self.post_process.clear_effects();
+    }
 
-        for texture in textures
-        {
-            texture_view_vec.push(&texture.view);
-            texture_sampler_vec.push(&texture.sampler);
+    pub fn clear(&mut self, red : f64, green: f64, blue: f64, alpha: f64) {
+        self.clear_color = wgpu::Color { r: red, g: green, b: blue, a: alpha };
+    }
+
+    /// Acquires the surface texture that every batch draws into until `present_frame`.
+    pub fn begin_frame(&mut self)
+    {
+        if self.frame.is_some() {
+            panic!("You can't call begin_frame twice without calling present_frame in between");
         }
 
-        let texture_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            entries: &[
-                wgpu::BindGroupEntry 
+        self.frame_count += 1;
+
+        self.reset_gpu_buffers();
+
+        let frame_count = self.frame_count;
+        self.texture_bind_groups.retain(|_, cached| cached.last_used_frame + BIND_GROUP_CACHE_FRAMES > frame_count);
+
+        self.device.poll(wgpu::Maintain::Poll);
+        self.pending_screenshots.retain(|screenshot| !screenshot.try_save());
+
+        let (output, view) = match (&self.surface, &self.offscreen_target)
+        {
+            (Some(surface), _) => match surface.get_current_texture()
+            {
+                Ok(output) => 
                 {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureViewArray(texture_view_vec.as_slice()),
-                },
-                wgpu::BindGroupEntry 
+                    let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+                    (Some(output), view)
+                }
+                Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => 
                 {
-                    binding: 1,
-                    resource: wgpu::BindingResource::SamplerArray(texture_sampler_vec.as_slice()),
+                    surface.configure(&self.device, &self.config);
+                    self.is_frame_skipped = true;
+                    return;
                 }
-            ],
-            layout: &self.texture_bind_group_layout,
-            label: Some("texture bind group"),
+                Err(wgpu::SurfaceError::OutOfMemory) => panic!("System has run out of memory"),
+                Err(wgpu::SurfaceError::Timeout) => 
+                {
+                    warn!("Surface timeout");
+                    self.is_frame_skipped = true;
+                    return;
+                }
+            },
+            (None, Some(offscreen_target)) => (None, offscreen_target.create_view(&wgpu::TextureViewDescriptor::default())),
+            (None, None) => panic!("Graphics interface has neither a surface nor an offscreen target"),
+        };
+
+        let (width, height) = self.target_size();
+        self.post_process.prepare(&self.device, width, height);
+        let is_post_processed = self.post_process.scene_view().is_some();
+
+        let capture = if output.is_some() && !self.screenshot_requests.is_empty() {
+            Some(CaptureTarget::new(&self.device, &self.config, &self.blit_bind_group_layout, &self.blit_sampler))
+        }
+        else {
+            None
+        };
+
+        self.frame = Some(Frame { output, view, capture, is_cleared: false, is_post_processed });
+    }
+
+    pub fn present_frame(&mut self)
+    {
+        let frame = match self.frame.take()
+        {
+            Some(frame) => frame,
+            None => 
+            {
+                self.is_frame_skipped = false;
+                return;
+            }
+        };
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Present Encoder"),
         });
-        
-        let sprite_vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: wgpu::BufferUsages::VERTEX,
+
+        if !frame.is_cleared {
+            self.clear_renderpass(self.frame_target_view(&frame), &mut encoder);
+        }
+
+        if frame.is_post_processed
+        {
+            let destination = match &self.virtual_resolution
+            {
+                Some(virtual_resolution) => &virtual_resolution.view,
+                None => frame.draw_view(),
+            };
+
+            self.post_process.run(&self.device, &self.queue, destination, &mut encoder);
+        }
+
+        if let Some(virtual_resolution) = &self.virtual_resolution 
+        {
+            let viewport = virtual_resolution.viewport(self.config.width, self.config.height);
+            self.blit_renderpass(&virtual_resolution.bind_group, &viewport, frame.draw_view(), &mut encoder);
+        }
+
+        let captured_texture = match (&frame.capture, &self.offscreen_target)
+        {
+            (Some(capture), _) => Some(&capture.texture),
+            (None, Some(offscreen_target)) => Some(offscreen_target),
+            (None, None) => None,
+        };
+
+        let mut screenshots = Vec::new();
+
+        if let Some(texture) = captured_texture
+        {
+            for directory in self.screenshot_requests.drain(..) 
+            {
+                if let Some(screenshot) = PendingScreenshot::new(&self.device, &mut encoder, texture, &self.config, &directory) {
+                    screenshots.push(screenshot);
+                }
+            }
+        }
+
+        if let Some(capture) = &frame.capture 
+        {
+            let viewport = Rectangle::new(Vector2::new(0.0, 0.0), self.config.width as f32, self.config.height as f32);
+            self.blit_renderpass(&capture.bind_group, &viewport, &frame.view, &mut encoder);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        for mut screenshot in screenshots 
+        {
+            screenshot.map();
+            self.pending_screenshots.push(screenshot);
+        }
+
+        if let Some(output) = frame.output {
+            output.present();
+        }
+    }
+
+    /// Saves the next presented frame as a timestamped PNG in `directory` without stalling,
+    /// the copy is read back over the following frames and encoded on another thread.
+    pub fn take_screenshot(&mut self, directory: impl AsRef<Path>) {
+        self.screenshot_requests.push(directory.as_ref().to_path_buf());
+    }
+
+    /// Copies the last presented frame of a headless interface back from the GPU, waiting for it
+    /// to finish rendering. Returns `None` for an interface drawing to a window.
+    pub fn read_frame(&self) -> Option<RgbaImage>
+    {
+        let offscreen_target = self.offscreen_target.as_ref()?;
+        let (width, height) = (self.config.width, self.config.height);
+
+        let unpadded_bytes_per_row = width * 4;
+        let padded_bytes_per_row = wgpu::util::align_to(unpadded_bytes_per_row, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+        let readback_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frame Readback Buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
         });
 
-        let sprite_index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(&indices),
-            usage: wgpu::BufferUsages::INDEX,
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
         });
 
+        encoder.copy_texture_to_buffer(
+            offscreen_target.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: NonZeroU32::new(height),
+                },
+            },
+            wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        );
+
+        self.queue.submit(iter::once(encoder.finish()));
+
+        let buffer_slice = readback_buffer.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        buffer_slice.map_async(wgpu::MapMode::Read, move |result| { let _ = sender.send(result); });
+        self.device.poll(wgpu::Maintain::Wait);
+
+        match receiver.recv()
         {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
+            Ok(Ok(())) => (),
+            _ => 
+            {
+                warn!("Failed to map the frame readback buffer");
+                return None;
+            }
+        }
+
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+
+        for row in buffer_slice.get_mapped_range().chunks(padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
 
-            render_pass.set_pipeline(&self.sprite_render_pipeline);
-            render_pass.set_bind_group(0, &texture_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, sprite_vertex_buffer.slice(..));
-            render_pass.set_index_buffer(sprite_index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..indices.len() as u32, 0, 0..1);
-        } 
+        readback_buffer.unmap();
+        RgbaImage::from_raw(width, height, pixels)
     }
 
-    fn rectangle_renderpass(&mut self, view: &TextureView, encoder: &mut CommandEncoder, vertices: &Vec<ShapeVertex>, indices: &Vec<u16>)
+    pub fn batch_render(&mut self, batch: &BatchData)
+    { 
+        if self.frame.is_none() && batch.render_target.is_none()
+        {
+            if !self.is_frame_skipped {
+                warn!("Batch rendered outside of begin_frame and present_frame, it won't be drawn");
+            }
+
+            return;
+        }
+
+        // Render target batches drawn between frames would otherwise keep moving the cursors on until
+        // the next begin_frame, growing the buffers. Every batch is submitted straight away, so the
+        // regions of earlier batches are no longer needed.
+        if self.frame.is_none() {
+            self.reset_gpu_buffers();
+        }
+
+        self.internal_batch_render(batch);
+
+        if batch.render_target.is_some() {
+            return;
+        }
+
+        if let Some(frame) = &mut self.frame {
+            frame.is_cleared = true;
+        }
+    }
+
+    fn reset_gpu_buffers(&mut self)
     {
+        self.sprite_vertex_buffer.reset();
+        self.sprite_index_buffer.reset();
+        self.shape_vertex_buffer.reset();
+        self.shape_index_buffer.reset();
+    }
 
-        let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+    fn internal_batch_render(&mut self, batch: &BatchData)
+    {
+        let sprite_vertices = self.sprite_vertex_buffer.write(&self.device, &self.queue, bytemuck::cast_slice(batch.sprite_vertices));
+        let sprite_indices = self.sprite_index_buffer.write(&self.device, &self.queue, bytemuck::cast_slice(batch.sprite_indices));
+        let shape_vertices = self.shape_vertex_buffer.write(&self.device, &self.queue, bytemuck::cast_slice(batch.shape_vertices));
+        let shape_indices = self.shape_index_buffer.write(&self.device, &self.queue, bytemuck::cast_slice(batch.shape_indices));
+
+        let texture_bind_group_keys: Vec<Option<Vec<u64>>> = batch.commands.iter().map(|command| match command 
+        {
+            BatchCommand::Sprites { textures, .. } => Some(self.cache_texture_bind_group(textures)),
+            BatchCommand::Shapes { .. } => None,
+        }).collect();
 
-        let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(&indices),
-            usage: wgpu::BufferUsages::INDEX,
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Render Encoder"),
         });
 
+        let (view, (target_width, target_height)) = match (batch.render_target, &self.frame)
+        {
+            (Some(render_target), _) => (&render_target.view, (render_target.width, render_target.height)),
+            (None, Some(frame)) => 
+            {
+                let view = self.frame_target_view(frame);
+
+                if !frame.is_cleared {
+                    self.clear_renderpass(view, &mut encoder);
+                }
+
+                (view, self.target_size())
+            }
+            (None, None) => return,
+        };
+
+        // A scissor entirely off the target leaves nothing to draw.
+        let scissor = match &batch.scissor
+        {
+            Some(scissor) => Self::clamp_to_target(scissor, target_width, target_height),
+            None => Some((0, 0, target_width, target_height)),
+        };
+
+        // wgpu rejects viewports without any area, like a split screen pane of a minimised window.
+        let has_viewport_area = match &batch.viewport
+        {
+            Some(viewport) => viewport.width > 0.0 && viewport.height > 0.0,
+            None => true,
+        };
 
+        if !batch.commands.is_empty() && scissor.is_some() && has_viewport_area
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Load,
@@ -435,48 +1048,182 @@ impl GraphicsInterface
                 depth_stencil_attachment: None,
             });
 
-            render_pass.set_pipeline(&self.rectangle_render_pipeline);
-            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-            render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..indices.len() as u32, 0, 0..1);
-        } 
+            if let Some(viewport) = &batch.viewport {
+                render_pass.set_viewport(viewport.left(), viewport.top(), viewport.width, viewport.height, 0.0, 1.0);
+            }
+
+            if let Some((scissor_x, scissor_y, scissor_width, scissor_height)) = scissor {
+                render_pass.set_scissor_rect(scissor_x, scissor_y, scissor_width, scissor_height);
+            }
+
+            for (command, texture_bind_group_key) in batch.commands.iter().zip(&texture_bind_group_keys)
+            {
+                // wgpu can't bind an empty buffer slice, which is all an empty command would have.
+                let command_indices = match command
+                {
+                    BatchCommand::Sprites { indices, .. } | BatchCommand::Shapes { indices, .. } => indices,
+                };
+
+                if command_indices.is_empty() {
+                    continue;
+                }
+
+                match (command, texture_bind_group_key)
+                {
+                    (BatchCommand::Sprites { indices, blend_mode, .. }, Some(texture_bind_group_key)) =>
+                    {
+                        match batch.material
+                        {
+                            Some(material) => 
+                            {
+                                render_pass.set_pipeline(&material.pipelines[blend_mode.index()]);
+                                render_pass.set_bind_group(1, &material.bind_group, &[]);
+                            }
+                            None => render_pass.set_pipeline(&self.sprite_render_pipelines[blend_mode.index()]),
+                        }
+
+                        render_pass.set_bind_group(0, &self.texture_bind_groups[texture_bind_group_key].bind_group, &[]);
+                        render_pass.set_vertex_buffer(0, self.sprite_vertex_buffer.buffer.slice(sprite_vertices.clone()));
+                        render_pass.set_index_buffer(self.sprite_index_buffer.buffer.slice(sprite_indices.clone()), wgpu::IndexFormat::Uint32);
+                        render_pass.draw_indexed(indices.clone(), 0, 0..1);
+                    }
+                    (BatchCommand::Shapes { indices, blend_mode }, _) =>
+                    {
+                        render_pass.set_pipeline(&self.shape_render_pipelines[blend_mode.index()]);
+                        render_pass.set_vertex_buffer(0, self.shape_vertex_buffer.buffer.slice(shape_vertices.clone()));
+                        render_pass.set_index_buffer(self.shape_index_buffer.buffer.slice(shape_indices.clone()), wgpu::IndexFormat::Uint32);
+                        render_pass.draw_indexed(indices.clone(), 0, 0..1);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));  
+    }
+
+    /// Where batches draw to, the post process scene while effects are running, otherwise the
+    /// virtual resolution target when there is one, otherwise the surface.
+    fn frame_target_view<'a>(&'a self, frame: &'a Frame) -> &'a TextureView
+    {
+        if frame.is_post_processed
+        {
+            if let Some(scene_view) = self.post_process.scene_view() {
+                return scene_view;
+            }
+        }
+
+        match &self.virtual_resolution
+        {
+            Some(virtual_resolution) => &virtual_resolution.view,
+            None => frame.draw_view(),
+        }
     }
 
-    fn circle_renderpass(&mut self, view: &TextureView, encoder: &mut CommandEncoder, vertices: &Vec<ShapeVertex>, indices: &Vec<u16>)
+    /// The part of `rectangle` inside a `width` by `height` target as whole pixels, `None` when nothing of it is left.
+    fn clamp_to_target(rectangle: &Rectangle, width: u32, height: u32) -> Option<(u32, u32, u32, u32)>
     {
+        let left = rectangle.left().max(0.0).round() as u32;
+        let top = rectangle.top().max(0.0).round() as u32;
+        let right = (rectangle.right().round().max(0.0) as u32).min(width);
+        let bottom = (rectangle.bottom().round().max(0.0) as u32).min(height);
 
-        let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: wgpu::BufferUsages::VERTEX,
+        if right <= left || bottom <= top {
+            return None;
+        }
+
+        Some((left, top, right - left, bottom - top))
+    }
+
+    fn clear_renderpass(&self, view: &TextureView, encoder: &mut CommandEncoder)
+    {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Clear Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.clear_color),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
         });
+    }
 
-        let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(&indices),
-            usage: wgpu::BufferUsages::INDEX,
+    /// Scales the texture behind `bind_group` into `viewport` of `view`, clearing the rest to black.
+    fn blit_renderpass(&self, bind_group: &wgpu::BindGroup, viewport: &Rectangle, view: &TextureView, encoder: &mut CommandEncoder)
+    {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Blit Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
         });
 
+        render_pass.set_pipeline(&self.blit_render_pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.set_viewport(viewport.left(), viewport.top(), viewport.width, viewport.height, 0.0, 1.0);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    /// Makes sure a bind group for `textures` is cached and returns the key it is stored under.
+    fn cache_texture_bind_group(&mut self, textures: &[Arc<Texture>]) -> Vec<u64>
+    {
+        let key: Vec<u64> = textures.iter().map(|texture| texture.id).collect();
 
+        if !self.texture_bind_groups.contains_key(&key)
         {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
+            let bind_group = self.create_texture_bind_group(textures);
+            self.texture_bind_groups.insert(key.clone(), CachedBindGroup { bind_group, last_used_frame: self.frame_count });
+        }
+
+        if let Some(cached) = self.texture_bind_groups.get_mut(&key) {
+            cached.last_used_frame = self.frame_count;
+        }
 
-            render_pass.set_pipeline(&self.circle_render_pipeline);
-            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-            render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..indices.len() as u32, 0, 0..1);
-        } 
+        key
     }
 
-}
\ No newline at end of file
+    fn create_texture_bind_group(&self, textures: &[Arc<Texture>]) -> wgpu::BindGroup
+    {
+        let mut texture_view_vec: Vec<&TextureView> = Vec::with_capacity(textures.len());
+        let mut texture_sampler_vec: Vec<&Sampler> = Vec::with_capacity(textures.len());
+
+        for texture in textures
+        {
+            texture_view_vec.push(&texture.view);
+            texture_sampler_vec.push(&texture.sampler);
+        }
+
+        let (texture_resource, sampler_resource) = if self.max_batch_textures > 1 {
+            (wgpu::BindingResource::TextureViewArray(texture_view_vec.as_slice()), wgpu::BindingResource::SamplerArray(texture_sampler_vec.as_slice()))
+        }
+        else {
+            (wgpu::BindingResource::TextureView(texture_view_vec[0]), wgpu::BindingResource::Sampler(texture_sampler_vec[0]))
+        };
+
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            entries: &[
+                wgpu::BindGroupEntry 
+                {
+                    binding: 0,
+                    resource: texture_resource,
+                },
+                wgpu::BindGroupEntry 
+                {
+                    binding: 1,
+                    resource: sampler_resource,
+                }
+            ],
+            layout: &self.texture_bind_group_layout,
+            label: Some("texture bind group"),
+        })
+    }
+}
@@ -0,0 +1,13 @@
+// Appended to every material, wraps its `fragment` function in the entry points the blend modes use.
+@fragment
+fn fs_main(in: VertexOutput) ->  @location(0) vec4<f32> 
+{
+    return fragment(in);
+}
+
+@fragment
+fn fs_premultiply(in: VertexOutput) ->  @location(0) vec4<f32> 
+{
+    let color = fragment(in);
+    return vec4<f32>(color.rgb * color.a, color.a);
+}
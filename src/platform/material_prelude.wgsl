@@ -0,0 +1,46 @@
+// Prepended to every material after the texture bindings, a material only has to define
+// `fn fragment(in: VertexOutput) -> vec4<f32>` and can declare its uniforms at
+// `@group(1) @binding(0)`.
+struct VertexInput {
+    @location(0) index: i32,
+    @location(1) position: vec2<f32>,
+    @location(2) tex_coords: vec2<f32>,
+    @location(3) color: vec4<f32>
+};
+
+struct VertexOutput 
+{
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) index: i32,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) color: vec4<f32>
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput 
+{
+    var out: VertexOutput;
+    out.index = in.index;
+    out.tex_coords = in.tex_coords;
+    out.clip_position = vec4<f32>(in.position, 0.0, 1.0);
+    out.color = in.color;
+    return out;
+}
+
+// The sprite's texture at its own texture coordinates, without the draw colour applied.
+fn sample_texture(in: VertexOutput) -> vec4<f32> 
+{
+    return sample_sprite(in.index, in.tex_coords);
+}
+
+// The sprite's texture at `tex_coords`, for effects that read neighbouring texels like outlines.
+fn sample_texture_at(in: VertexOutput, tex_coords: vec2<f32>) -> vec4<f32> 
+{
+    return sample_sprite(in.index, tex_coords);
+}
+
+// Size of the sprite's texture in texels.
+fn texture_size(in: VertexOutput) -> vec2<f32> 
+{
+    return sprite_texture_size(in.index);
+}
@@ -0,0 +1,6 @@
+// Appended to every post effect.
+@fragment
+fn fs_main(in: VertexOutput) ->  @location(0) vec4<f32> 
+{
+    return effect(in.tex_coords);
+}
@@ -0,0 +1,38 @@
+// Prepended to every post effect, an effect only has to define
+// `fn effect(uv: vec2<f32>) -> vec4<f32>` and can declare its uniforms at
+// `@group(1) @binding(0)`.
+struct VertexOutput 
+{
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+// One triangle that covers the whole target, the corners past the edges get clipped.
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput 
+{
+    var out: VertexOutput;
+    let x = f32((vertex_index << 1u) & 2u);
+    let y = f32(vertex_index & 2u);
+
+    out.tex_coords = vec2<f32>(x, y);
+    out.clip_position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    return out;
+}
+
+@group(0) @binding(0)
+var source_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+// The frame as the previous pass left it, `uv` is 0,0 at the top left and 1,1 at the bottom right.
+fn sample_source(uv: vec2<f32>) -> vec4<f32> 
+{
+    return textureSample(source_texture, source_sampler, uv);
+}
+
+// Size of the frame in pixels.
+fn source_size() -> vec2<f32> 
+{
+    return vec2<f32>(textureDimensions(source_texture));
+}
@@ -0,0 +1,315 @@
+use std::sync::{Arc, Mutex};
+use wgpu::{CommandEncoder, TextureView};
+use crate::graphics::post_effect::PostEffect;
+
+/// Size the uniform buffers are rounded up to, also what an effect without uniforms gets.
+const UNIFORM_ALIGNMENT: usize = 16;
+
+/// A texture the frame is drawn into so the next pass can read it.
+struct OffscreenTarget
+{
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+struct PostPass
+{
+    effect: Arc<Mutex<dyn PostEffect>>,
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    uniform_size: usize,
+    uniform_bind_group: wgpu::BindGroup,
+}
+
+/// The chain of post effects, batches draw into the first offscreen target while it is active
+/// and each pass reads the previous result, the last one writing to the frame's real target.
+pub struct PostProcess
+{
+    format: wgpu::TextureFormat,
+    source_bind_group_layout: wgpu::BindGroupLayout,
+    uniform_bind_group_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    sampler: wgpu::Sampler,
+    passes: Vec<PostPass>,
+    /// Passes at the front of `passes` that were cleared and are dropped at the next `prepare`,
+    /// a frame that already draws into the scene still needs them to reach the screen.
+    cleared_passes: usize,
+    targets: Vec<OffscreenTarget>,
+    target_size: (u32, u32),
+}
+
+impl PostProcess
+{
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self
+    {
+        let source_bind_group_layout =
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            label: Some("post_source_bind_group_layout"),
+        });
+
+        let uniform_bind_group_layout =
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            label: Some("post_uniform_bind_group_layout"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post Effect Pipeline Layout"),
+            bind_group_layouts: &[&source_bind_group_layout, &uniform_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self { format, source_bind_group_layout, uniform_bind_group_layout, pipeline_layout, sampler, passes: Vec::new(), cleared_passes: 0, targets: Vec::new(), target_size: (0, 0) }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.passes.len() > self.cleared_passes
+    }
+
+    /// Compiles `effect` and adds it to the end of the chain.
+    pub fn add_effect(&mut self, device: &wgpu::Device, effect: Arc<Mutex<dyn PostEffect>>)
+    {
+        let (shader_source, uniforms) = match effect.lock()
+        {
+            Ok(effect) => (effect.shader(), effect.uniforms()),
+            Err(_) => panic!("Post effect was poisoned by a panic while it was locked"),
+        };
+
+        let source = format!("{}\n{}\n{}", include_str!("post_prelude.wgsl"), shader_source, include_str!("post_entry.wgsl"));
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Post Effect Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Post Effect Pipeline"),
+            layout: Some(&self.pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: self.format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let uniform_size = Self::uniform_size(&uniforms);
+        let (uniform_buffer, uniform_bind_group) = self.create_uniforms(device, uniform_size);
+
+        self.passes.push(PostPass { effect, pipeline, uniform_buffer, uniform_size, uniform_bind_group });
+    }
+
+    pub fn clear_effects(&mut self) {
+        self.cleared_passes = self.passes.len();
+    }
+
+    /// Makes sure the offscreen targets match the size of the frame, called at the start of every frame.
+    pub fn prepare(&mut self, device: &wgpu::Device, width: u32, height: u32)
+    {
+        self.passes.drain(..self.cleared_passes);
+        self.cleared_passes = 0;
+
+        if !self.is_active()
+        {
+            self.targets.clear();
+            return;
+        }
+
+        if self.targets.is_empty() || self.target_size != (width, height)
+        {
+            self.targets = (0..2).map(|_| self.create_target(device, width, height)).collect();
+            self.target_size = (width, height);
+        }
+    }
+
+    /// Where batches draw while the chain is active.
+    pub fn scene_view(&self) -> Option<&TextureView> {
+        self.targets.first().map(|target| &target.view)
+    }
+
+    /// Records every pass, reading the scene and leaving the result in `destination`.
+    pub fn run(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, destination: &TextureView, encoder: &mut CommandEncoder)
+    {
+        if self.passes.is_empty() || self.targets.len() < 2 {
+            return;
+        }
+
+        for index in 0..self.passes.len()
+        {
+            let uniforms = match self.passes[index].effect.lock()
+            {
+                Ok(effect) => effect.uniforms(),
+                Err(_) => panic!("Post effect was poisoned by a panic while it was locked"),
+            };
+
+            let uniform_size = Self::uniform_size(&uniforms);
+
+            if uniform_size != self.passes[index].uniform_size
+            {
+                let (uniform_buffer, uniform_bind_group) = self.create_uniforms(device, uniform_size);
+                let pass = &mut self.passes[index];
+                pass.uniform_buffer = uniform_buffer;
+                pass.uniform_bind_group = uniform_bind_group;
+                pass.uniform_size = uniform_size;
+            }
+
+            if !uniforms.is_empty()
+            {
+                let mut contents = uniforms;
+                contents.resize(uniform_size, 0);
+                queue.write_buffer(&self.passes[index].uniform_buffer, 0, &contents);
+            }
+        }
+
+        let last = self.passes.len() - 1;
+
+        for (index, pass) in self.passes.iter().enumerate()
+        {
+            let source = &self.targets[index % 2];
+            let target = if index == last { destination } else { &self.targets[(index + 1) % 2].view };
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Post Effect Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            render_pass.set_pipeline(&pass.pipeline);
+            render_pass.set_bind_group(0, &source.bind_group, &[]);
+            render_pass.set_bind_group(1, &pass.uniform_bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+
+    fn uniform_size(uniforms: &[u8]) -> usize {
+        wgpu::util::align_to(uniforms.len().max(1), UNIFORM_ALIGNMENT)
+    }
+
+    fn create_uniforms(&self, device: &wgpu::Device, size: usize) -> (wgpu::Buffer, wgpu::BindGroup)
+    {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Post Effect Uniform Buffer"),
+            size: size as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            entries: &[
+                wgpu::BindGroupEntry
+                {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+            layout: &self.uniform_bind_group_layout,
+            label: Some("post effect uniform bind group"),
+        });
+
+        (uniform_buffer, uniform_bind_group)
+    }
+
+    fn create_target(&self, device: &wgpu::Device, width: u32, height: u32) -> OffscreenTarget
+    {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("post effect target"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            entries: &[
+                wgpu::BindGroupEntry
+                {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry
+                {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                }
+            ],
+            layout: &self.source_bind_group_layout,
+            label: Some("post effect target bind group"),
+        });
+
+        OffscreenTarget { view, bind_group }
+    }
+}
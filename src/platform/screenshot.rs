@@ -0,0 +1,215 @@
+use log::{info, warn};
+use image::RgbaImage;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fs, sync::mpsc, thread};
+
+/// Screenshots taken so far, added to file names so two taken in the same millisecond don't overwrite each other.
+static SCREENSHOT_COUNT: AtomicU32 = AtomicU32::new(0);
+
+/// A copy of the frame drawn into in place of the surface on frames a screenshot is taken,
+/// most backends can't copy out of a surface texture directly.
+pub struct CaptureTarget
+{
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl CaptureTarget
+{
+    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, bind_group_layout: &wgpu::BindGroupLayout, sampler: &wgpu::Sampler) -> Self
+    {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("screenshot capture"),
+            size: wgpu::Extent3d { width: config.width, height: config.height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            entries: &[
+                wgpu::BindGroupEntry
+                {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry
+                {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                }
+            ],
+            layout: bind_group_layout,
+            label: Some("screenshot capture bind group"),
+        });
+
+        Self { texture, view, bind_group }
+    }
+}
+
+/// A frame copied into a readback buffer, saved as a PNG once the GPU is done with it.
+pub struct PendingScreenshot
+{
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    is_bgra: bool,
+    path: PathBuf,
+    mapped: Option<mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>>,
+}
+
+impl PendingScreenshot
+{
+    /// Records the copy of `texture` into `encoder`, `None` when its format can't be saved as a PNG.
+    pub fn new(device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture, config: &wgpu::SurfaceConfiguration, directory: &Path) -> Option<Self>
+    {
+        let is_bgra = match config.format
+        {
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            format =>
+            {
+                warn!("Screenshots of {:?} frames aren't supported", format);
+                return None;
+            }
+        };
+
+        let (width, height) = (config.width, config.height);
+        let padded_bytes_per_row = wgpu::util::align_to(width * 4, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Screenshot Readback Buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: NonZeroU32::new(height),
+                },
+            },
+            wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        );
+
+        let path = directory.join(format!("screenshot_{}_{}.png", timestamp(), SCREENSHOT_COUNT.fetch_add(1, Ordering::Relaxed)));
+
+        Some(Self { buffer, width, height, padded_bytes_per_row, is_bgra, path, mapped: None })
+    }
+
+    /// Starts mapping the buffer, call once the copy has been submitted.
+    pub fn map(&mut self)
+    {
+        let (sender, receiver) = mpsc::channel();
+        self.buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| { let _ = sender.send(result); });
+        self.mapped = Some(receiver);
+    }
+
+    /// Hands the pixels to a thread to be encoded once the buffer is mapped, returns `true`
+    /// when the screenshot no longer needs the buffer.
+    pub fn try_save(&self) -> bool
+    {
+        let result = match &self.mapped
+        {
+            Some(mapped) => match mapped.try_recv()
+            {
+                Ok(result) => result,
+                Err(mpsc::TryRecvError::Empty) => return false,
+                Err(mpsc::TryRecvError::Disconnected) => Err(wgpu::BufferAsyncError),
+            },
+            None => return false,
+        };
+
+        if result.is_err()
+        {
+            warn!("Failed to read back the frame for {}", self.path.display());
+            return true;
+        }
+
+        let unpadded_bytes_per_row = (self.width * 4) as usize;
+        let mut pixels = Vec::with_capacity(unpadded_bytes_per_row * self.height as usize);
+
+        for row in self.buffer.slice(..).get_mapped_range().chunks(self.padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row]);
+        }
+
+        self.buffer.unmap();
+
+        let (width, height, is_bgra, path) = (self.width, self.height, self.is_bgra, self.path.clone());
+
+        thread::spawn(move ||
+        {
+            if is_bgra
+            {
+                for pixel in pixels.chunks_exact_mut(4) {
+                    pixel.swap(0, 2);
+                }
+            }
+
+            match save_png(pixels, width, height, &path)
+            {
+                Ok(()) => info!("Saved screenshot {}", path.display()),
+                Err(e) => warn!("Couldn't save screenshot {}, error message: {}", path.display(), e),
+            }
+        });
+
+        true
+    }
+}
+
+fn save_png(pixels: Vec<u8>, width: u32, height: u32, path: &Path) -> Result<(), String>
+{
+    let image = match RgbaImage::from_raw(width, height, pixels)
+    {
+        Some(image) => image,
+        None => return Err(String::from("pixels don't match the frame size")),
+    };
+
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).map_err(|e| e.to_string())?;
+    }
+
+    image.save_with_format(path, image::ImageFormat::Png).map_err(|e| e.to_string())
+}
+
+/// The current UTC time as `YYYY-MM-DD_HH-MM-SS-mmm`, safe to use in a file name.
+fn timestamp() -> String
+{
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    let time_of_day = seconds % 86_400;
+
+    format!("{:04}-{:02}-{:02}_{:02}-{:02}-{:02}-{:03}", year, month, day,
+            time_of_day / 3600, time_of_day / 60 % 60, time_of_day % 60, since_epoch.subsec_millis())
+}
+
+/// Days since 1970-01-01 to a (year, month, day) date, Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32)
+{
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
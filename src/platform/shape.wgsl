@@ -24,4 +24,11 @@ fn vs_main(in: VertexInput) -> VertexOutput
 fn fs_main(in: VertexOutput) ->  @location(0) vec4<f32> 
 {
     return in.color;
-}
\ No newline at end of file
+}
+
+@fragment
+fn fs_premultiply(in: VertexOutput) ->  @location(0) vec4<f32> 
+{
+    let color = in.color;
+    return vec4<f32>(color.rgb * color.a, color.a);
+}
@@ -25,14 +25,16 @@ fn vs_main(in: VertexInput) -> VertexOutput
     return out;
 }
 
-// Fragment shader
-@group(0) @binding(0)
-var texture_array: binding_array<texture_2d<f32>>;
-@group(0) @binding(1)
-var sampler_array: binding_array<sampler>;
-
+// Fragment shader, `sample_sprite` comes from texture_array.wgsl or texture_single.wgsl
 @fragment
 fn fs_main(in: VertexOutput) ->  @location(0) vec4<f32> 
 {
-    return in.color * textureSample(texture_array[in.index], sampler_array[in.index], in.tex_coords);
-}
\ No newline at end of file
+    return in.color * sample_sprite(in.index, in.tex_coords);
+}
+
+@fragment
+fn fs_premultiply(in: VertexOutput) ->  @location(0) vec4<f32> 
+{
+    let color = in.color * sample_sprite(in.index, in.tex_coords);
+    return vec4<f32>(color.rgb * color.a, color.a);
+}
@@ -1,3 +1,5 @@
+use crate::window::settings::{WindowSettings, WindowMode};
+
 pub struct SDLSystem
 {
     pub window: sdl2::video::Window,
@@ -7,7 +9,7 @@ pub struct SDLSystem
 
 impl SDLSystem  
 {
-    pub fn new(window_title: &str, width: u32, height: u32) -> Self
+    pub fn new(settings: &WindowSettings) -> Self
     {
         let sdl_context = match sdl2::init() 
         {
@@ -34,9 +36,21 @@ impl SDLSystem
             Err(e) => { panic!("couldn't create sdl game controller subsystem in system sdl, error message: {}", e) }
         };
 
-        let window = match video_subsystem
-            .window(window_title, width, height)
-            .position_centered()
+        let mut window_builder = video_subsystem.window(&settings.title, settings.width, settings.height);
+        window_builder.position_centered();
+
+        match settings.window_mode
+        {
+            WindowMode::Windowed => {},
+            WindowMode::Fullscreen => { window_builder.fullscreen(); },
+            WindowMode::BorderlessFullscreen => { window_builder.fullscreen_desktop(); },
+        }
+
+        if settings.resizable {
+            window_builder.resizable();
+        }
+
+        let mut window = match window_builder
             .build()
             .map_err(|e| e.to_string())
             {
@@ -44,6 +58,13 @@ impl SDLSystem
                 Err(e) => { panic!("couldn't create sdl window in system sdl, error message: {}", e) }
             };
 
+        if let Some((minimum_width, minimum_height)) = settings.minimum_size
+        {
+            if let Err(e) = window.set_minimum_size(minimum_width, minimum_height) {
+                panic!("couldn't set sdl window minimum size in system sdl, error message: {}", e)
+            }
+        }
+
         Self { window, event_pump, game_controller_subsystem }
     }
 }
\ No newline at end of file
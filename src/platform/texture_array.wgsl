@@ -0,0 +1,15 @@
+// Sprite textures when the adapter supports binding arrays, each vertex picks its texture by index.
+@group(0) @binding(0)
+var texture_array: binding_array<texture_2d<f32>>;
+@group(0) @binding(1)
+var sampler_array: binding_array<sampler>;
+
+fn sample_sprite(index: i32, tex_coords: vec2<f32>) -> vec4<f32> 
+{
+    return textureSample(texture_array[index], sampler_array[index], tex_coords);
+}
+
+fn sprite_texture_size(index: i32) -> vec2<f32> 
+{
+    return vec2<f32>(textureDimensions(texture_array[index]));
+}
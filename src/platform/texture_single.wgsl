@@ -0,0 +1,15 @@
+// Sprite textures on downlevel adapters, one texture is bound per draw so the index is always 0.
+@group(0) @binding(0)
+var sprite_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var sprite_sampler: sampler;
+
+fn sample_sprite(index: i32, tex_coords: vec2<f32>) -> vec4<f32> 
+{
+    return textureSample(sprite_texture, sprite_sampler, tex_coords);
+}
+
+fn sprite_texture_size(index: i32) -> vec2<f32> 
+{
+    return vec2<f32>(textureDimensions(sprite_texture));
+}
@@ -0,0 +1,81 @@
+use crate::math::Vector2;
+use crate::shapes::rectangle::Rectangle;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScalingMode
+{
+    /// Scales as large as fits while keeping the aspect ratio, bars fill the rest.
+    Letterbox,
+    /// Like `Letterbox` but only by whole multiples, so every pixel stays the same size.
+    /// Windows smaller than the virtual resolution fall back to `Letterbox`.
+    Integer,
+}
+
+pub struct VirtualResolution
+{
+    pub width: u32,
+    pub height: u32,
+    pub scaling_mode: ScalingMode,
+    pub view: wgpu::TextureView,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl VirtualResolution
+{
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, bind_group_layout: &wgpu::BindGroupLayout, sampler: &wgpu::Sampler, 
+               width: u32, height: u32, scaling_mode: ScalingMode) -> Self
+    {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("virtual resolution"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            entries: &[
+                wgpu::BindGroupEntry 
+                {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry 
+                {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                }
+            ],
+            layout: bind_group_layout,
+            label: Some("virtual resolution bind group"),
+        });
+
+        Self { width, height, scaling_mode, view, bind_group }
+    }
+
+    /// The area of a `surface_width` by `surface_height` surface the virtual image is scaled into.
+    pub fn viewport(&self, surface_width: u32, surface_height: u32) -> Rectangle
+    {
+        let scale_x = surface_width as f32 / self.width as f32;
+        let scale_y = surface_height as f32 / self.height as f32;
+
+        let mut scale = scale_x.min(scale_y);
+
+        // Below 1x there's no whole multiple that fits, so it scales down like `Letterbox` instead of cropping.
+        if self.scaling_mode == ScalingMode::Integer && scale >= 1.0 {
+            scale = scale.floor();
+        }
+
+        let width = self.width as f32 * scale;
+        let height = self.height as f32 * scale;
+
+        let x = ((surface_width as f32 - width) / 2.0).floor();
+        let y = ((surface_height as f32 - height) / 2.0).floor();
+
+        Rectangle::new(Vector2::new(x, y), width, height)
+    }
+}
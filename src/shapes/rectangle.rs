@@ -1,5 +1,6 @@
 use crate::math::Vector2;
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Rectangle
 {
     pub width: f32,
@@ -1,31 +1,50 @@
+pub mod settings;
+
 use crate::game::Game;
 use std::time::Instant;
+use sdl2::event::{Event, WindowEvent};
 use crate::input::Input;
+use crate::game_time::GameTime;
 use system_sdl::SDLSystem;
 use crate::platform::system_sdl;
+use settings::WindowSettings;
 use crate::graphics::draw::Draw;
 use crate::input::{ gamepad::Gamepad, keyboard::Keyboard, mouse::Mouse };
 use crate::platform::graphics_interface::GraphicsInterface;
 
 pub struct Window
 {
+    pub settings: WindowSettings,
     pub sdl2_system: SDLSystem,
     pub graphics_interface: GraphicsInterface,
 }
 
 impl Window 
 {
-    pub fn new(window_title: &str, width: u32, height: u32) -> Self
+    pub fn new(window_title: &str, width: u32, height: u32) -> Self {
+        Self::from_settings(WindowSettings::new(window_title, width, height))
+    }
+
+    pub fn from_settings(settings: WindowSettings) -> Self
     {
-        let sdl2_system = SDLSystem::new(window_title, width, height);
-        let graphics_interface = match GraphicsInterface::new(&sdl2_system)
+        // The fields are public, so the checks in the builder calls can be skipped.
+        assert!(settings.update_rate.is_finite() && settings.update_rate > 0.0, "update rate must be positive, got {}", settings.update_rate);
+        assert!(settings.max_updates_per_frame > 0, "at least one update per frame is needed");
+
+        let sdl2_system = SDLSystem::new(&settings);
+        let mut graphics_interface = match GraphicsInterface::new(&sdl2_system, &settings)
         {
             Ok(graphics_interface) => { graphics_interface },
             Err(e) => {   
                 panic!("couldn't create graphics interface for window, error message: {}", e) 
             }
         };
-        Self { sdl2_system, graphics_interface }
+
+        if let Some((width, height, scaling_mode)) = settings.virtual_resolution {
+            graphics_interface.set_virtual_resolution(width, height, scaling_mode);
+        }
+
+        Self { settings, sdl2_system, graphics_interface }
     }
 }
 
@@ -41,6 +60,10 @@ pub fn run(mut window: Window, mut game: impl Game) -> Result<(), String>
     let mut input = Input::new(mouse, gamepad, keyboard);
 
 
+    let update_time = 1.0 / window.settings.update_rate;
+    let max_updates_per_frame = window.settings.max_updates_per_frame;
+    let mut game_time = GameTime::new(update_time as f32);
+
     let mut accumulator = 0.0;
     let timer = Instant::now();
     let mut previous_time = timer.elapsed().as_secs_f64();
@@ -52,19 +75,56 @@ pub fn run(mut window: Window, mut game: impl Game) -> Result<(), String>
         previous_time = current_time;
         accumulator += elapsed_time;
 
-        for event in window.sdl2_system.event_pump.poll_iter() {
+        let mut resized_to = None;
+
+        for event in window.sdl2_system.event_pump.poll_iter() 
+        {
+            if let Event::Window { win_event: WindowEvent::Resized(width, height) | WindowEvent::SizeChanged(width, height), .. } = event {
+                resized_to = Some((width as u32, height as u32));
+            }
+
             input.poll(&window.sdl2_system.game_controller_subsystem, &event);
         }
 
+        if let Some((width, height)) = resized_to.filter(|&(width, height)| width > 0 && height > 0)
+        {
+            sprite_batch.graphics_interface.resize(width, height);
+            game.on_resize(width, height);
+        }
+
+        let (mouse_offset, mouse_scale) = sprite_batch.graphics_interface.virtual_transform();
+        input.mouse.set_transform(mouse_offset, mouse_scale);
+
         game.process_input(&mut input);
 
-        while accumulator >= 1.0 / 60.0
+        if let Some(screenshot_key) = window.settings.screenshot_key
         {
-            game.update();
-            accumulator -= 1.0 / 60.0;
+            if input.keyboard.is_pressed(screenshot_key) {
+                sprite_batch.take_screenshot(&window.settings.screenshot_directory);
+            }
+        }
+
+        let mut update_count = 0;
+
+        while accumulator >= update_time
+        {
+            if update_count == max_updates_per_frame 
+            {
+                accumulator %= update_time;
+                break;
+            }
+
+            game.update(&game_time);
+            game_time.total_time += update_time;
+            accumulator -= update_time;
+            update_count += 1;
         }
 
-        game.draw(&mut sprite_batch);
+        game_time.alpha = (accumulator / update_time) as f32;
+        sprite_batch.begin_frame();
+        game.draw(&mut sprite_batch, &game_time);
+        sprite_batch.present();
+        game_time.frame_count += 1;
         input.clear();
     }
 
@@ -0,0 +1,126 @@
+pub use wgpu::{Backends, PresentMode};
+use std::path::PathBuf;
+use crate::input::keyboard::Key;
+use crate::platform::virtual_resolution::ScalingMode;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WindowMode
+{
+    Windowed,
+    Fullscreen,
+    BorderlessFullscreen,
+}
+
+/// Everything `Window::from_settings` and `run` need to know, built with chained calls:
+///
+/// `WindowSettings::new("game", 1280, 720).resizable(true).vsync(true).update_rate(120.0)`
+#[derive(Clone, Debug)]
+pub struct WindowSettings
+{
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+    pub window_mode: WindowMode,
+    pub resizable: bool,
+    pub present_mode: PresentMode,
+    pub update_rate: f64,
+    pub max_updates_per_frame: u32,
+    pub minimum_size: Option<(u32, u32)>,
+    pub virtual_resolution: Option<(u32, u32, ScalingMode)>,
+    /// Backends tried before the primary ones and then the secondary ones.
+    pub backends: Option<Backends>,
+    /// Key that saves a screenshot to `screenshot_directory` when pressed.
+    pub screenshot_key: Option<Key>,
+    pub screenshot_directory: PathBuf,
+}
+
+impl WindowSettings
+{
+    pub fn new(title: &str, width: u32, height: u32) -> Self
+    {
+        Self
+        {
+            title: title.to_string(),
+            width,
+            height,
+            window_mode: WindowMode::Windowed,
+            resizable: false,
+            present_mode: PresentMode::Immediate,
+            update_rate: 60.0,
+            max_updates_per_frame: 5,
+            minimum_size: None,
+            virtual_resolution: None,
+            backends: None,
+            screenshot_key: None,
+            screenshot_directory: PathBuf::from("screenshots"),
+        }
+    }
+
+    pub fn window_mode(mut self, window_mode: WindowMode) -> Self {
+        self.window_mode = window_mode;
+        self
+    }
+
+    pub fn resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    /// Shorthand for `present_mode`, `true` waits for vertical blank and `false` presents immediately.
+    pub fn vsync(mut self, vsync: bool) -> Self {
+        self.present_mode = if vsync { PresentMode::Fifo } else { PresentMode::Immediate };
+        self
+    }
+
+    pub fn present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.present_mode = present_mode;
+        self
+    }
+
+    /// Number of fixed updates per second, panics unless it's positive and finite.
+    pub fn update_rate(mut self, update_rate: f64) -> Self
+    {
+        assert!(update_rate.is_finite() && update_rate > 0.0, "update rate must be positive, got {}", update_rate);
+        self.update_rate = update_rate;
+        self
+    }
+
+    /// Caps the updates run in one frame, any time still left over is dropped so
+    /// a slow frame can't snowball into ever more updates. Panics when 0, which would never update.
+    pub fn max_updates_per_frame(mut self, max_updates_per_frame: u32) -> Self
+    {
+        assert!(max_updates_per_frame > 0, "at least one update per frame is needed");
+        self.max_updates_per_frame = max_updates_per_frame;
+        self
+    }
+
+    pub fn minimum_size(mut self, width: u32, height: u32) -> Self {
+        self.minimum_size = Some((width, height));
+        self
+    }
+
+    /// Renders at a fixed `width` by `height` and scales the result to the window.
+    pub fn virtual_resolution(mut self, width: u32, height: u32, scaling_mode: ScalingMode) -> Self {
+        self.virtual_resolution = Some((width, height, scaling_mode));
+        self
+    }
+
+    /// Backends tried first, for example `Backends::VULKAN` or `Backends::GL`, the primary and then the
+    /// secondary backends are still tried if none of these has an adapter.
+    /// The `WGPU_BACKEND` environment variable still takes precedence.
+    pub fn backends(mut self, backends: Backends) -> Self {
+        self.backends = Some(backends);
+        self
+    }
+
+    pub fn screenshot_key(mut self, key: Key) -> Self {
+        self.screenshot_key = Some(key);
+        self
+    }
+
+    /// Where screenshots are saved, relative paths start from the working directory.
+    pub fn screenshot_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.screenshot_directory = directory.into();
+        self
+    }
+}
\ No newline at end of file
@@ -0,0 +1,513 @@
+use image::RgbaImage;
+use std::fs;
+use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use baldimore::graphics::draw::Draw;
+use baldimore::graphics::font::Font;
+use baldimore::graphics::bitmap_font::BitmapFont;
+use baldimore::graphics::batch::{BatchSettings, SortMode};
+use baldimore::graphics::camera::Camera2D;
+use baldimore::graphics::render_target::RenderTarget;
+use baldimore::graphics::sprite_options::SpriteOptions;
+use baldimore::graphics::texture::Texture;
+use baldimore::graphics::text::layout::TextSettings;
+use baldimore::graphics::colour::Colour;
+use baldimore::shapes::rectangle::Rectangle;
+use baldimore::math::{Matrix4, SquareMatrix, Vector2};
+use baldimore::platform::virtual_resolution::ScalingMode;
+use baldimore::platform::graphics_interface::GraphicsInterface;
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 64;
+
+/// Fonts found on common desktop and CI machines, the font tests are skipped without one.
+const SYSTEM_FONTS: [&str; 4] = [
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "/Library/Fonts/Arial.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+
+/// Set to skip the headless tests on machines without any adapter, not even a software one.
+const SKIP_WITHOUT_ADAPTER: &str = "BALDIMORE_SKIP_WITHOUT_ADAPTER";
+
+/// Set to write the frames golden image tests render as their new reference images.
+const UPDATE_GOLDEN_IMAGES: &str = "BALDIMORE_UPDATE_GOLDEN_IMAGES";
+
+/// A `Draw` rendering offscreen. Without an adapter the test fails, unless `SKIP_WITHOUT_ADAPTER`
+/// is set, then this is `None` and the test is skipped.
+fn headless_draw() -> Option<Draw>
+{
+    match GraphicsInterface::new_headless(WIDTH, HEIGHT)
+    {
+        Ok(graphics_interface) => Some(Draw::new(graphics_interface)),
+        Err(e) if std::env::var_os(SKIP_WITHOUT_ADAPTER).is_some() =>
+        {
+            eprintln!("Skipping headless test, no adapter available: {}", e);
+            None
+        }
+        Err(e) => panic!("No adapter available for the headless tests, set {} to skip them: {}", SKIP_WITHOUT_ADAPTER, e),
+    }
+}
+
+/// First of `SYSTEM_FONTS` on this machine.
+fn system_font() -> Option<&'static str>
+{
+    let font_path = SYSTEM_FONTS.iter().find(|path| Path::new(path).exists()).copied();
+
+    if font_path.is_none() {
+        eprintln!("Skipping font test, no system font found");
+    }
+
+    font_path
+}
+
+/// Writes a one glyph text `.fnt` whose page is white on top and transparent below, `A` covering the top.
+fn write_bitmap_font(name: &str, glyph_page: usize) -> PathBuf
+{
+    let directory = std::env::temp_dir().join(format!("baldimore_{}", name));
+
+    if let Err(e) = fs::create_dir_all(&directory) {
+        panic!("Couldn't create {}: {}", directory.display(), e);
+    }
+
+    let page = RgbaImage::from_fn(16, 16, |_, y| if y < 8 { image::Rgba([255, 255, 255, 255]) } else { image::Rgba([0, 0, 0, 0]) });
+
+    if let Err(e) = page.save(directory.join("page.png")) {
+        panic!("Couldn't write the bitmap font page: {}", e);
+    }
+
+    let description = format!("info face=\"test\" size=8\n\
+                               common lineHeight=8 base=7 scaleW=16 scaleH=16 pages=1\n\
+                               page id=0 file=\"page.png\"\n\
+                               chars count=1\n\
+                               char id=65 x=0 y=0 width=8 height=8 xoffset=0 yoffset=0 xadvance=8 page={}\n", glyph_page);
+
+    let font_path = directory.join("font.fnt");
+
+    if let Err(e) = fs::write(&font_path, description) {
+        panic!("Couldn't write the bitmap font: {}", e);
+    }
+
+    font_path
+}
+
+/// Draws one frame on a black background and reads it back.
+fn render(draw: &mut Draw, draw_frame: impl FnOnce(&mut Draw)) -> RgbaImage
+{
+    draw.clear(Colour::BLACK);
+    draw.begin_frame();
+    draw.begin(Matrix4::identity());
+    draw_frame(draw);
+    draw.end();
+    draw.present();
+
+    match draw.read_frame()
+    {
+        Some(frame) => frame,
+        None => panic!("Headless frame couldn't be read back"),
+    }
+}
+
+fn is_close(actual: [u8; 4], expected: [u8; 4]) -> bool {
+    actual.iter().zip(expected.iter()).all(|(actual, expected)| actual.abs_diff(*expected) <= 2)
+}
+
+fn assert_pixel(frame: &RgbaImage, x: u32, y: u32, expected: [u8; 4])
+{
+    let actual = frame.get_pixel(x, y).0;
+
+    assert!(is_close(actual, expected), "pixel ({}, {}) is {:?}, expected {:?}", x, y, actual, expected);
+}
+
+/// Compares `frame` pixel by pixel with `tests/golden/{name}.png`. On a mismatch the frame is written
+/// next to the temporary files to look at, `UPDATE_GOLDEN_IMAGES` replaces the reference instead.
+fn assert_matches_golden_image(frame: &RgbaImage, name: &str)
+{
+    let golden_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(format!("{}.png", name));
+
+    if std::env::var_os(UPDATE_GOLDEN_IMAGES).is_some()
+    {
+        if let Err(e) = frame.save(&golden_path) {
+            panic!("Couldn't write golden image {}: {}", golden_path.display(), e);
+        }
+
+        return;
+    }
+
+    let golden = match image::open(&golden_path)
+    {
+        Ok(golden) => golden.to_rgba8(),
+        Err(e) => panic!("Couldn't read golden image {}, set {} to create it: {}", golden_path.display(), UPDATE_GOLDEN_IMAGES, e),
+    };
+
+    assert_eq!(frame.dimensions(), golden.dimensions(), "frame and golden image {} differ in size", name);
+
+    let differing_pixels = frame.pixels().zip(golden.pixels()).filter(|(actual, expected)| !is_close(actual.0, expected.0)).count();
+
+    if differing_pixels > 0
+    {
+        let actual_path = std::env::temp_dir().join(format!("baldimore_{}_actual.png", name));
+        let _ = frame.save(&actual_path);
+
+        panic!("{} pixels differ from golden image {}, the frame was saved to {}", differing_pixels, golden_path.display(), actual_path.display());
+    }
+}
+
+#[test]
+fn fill_rectangle_reads_back()
+{
+    let mut draw = match headless_draw()
+    {
+        Some(draw) => draw,
+        None => return,
+    };
+
+    let frame = render(&mut draw, |draw| draw.fill_rectangle(&Rectangle::new(Vector2::new(16.0, 16.0), 16.0, 16.0), Colour::RED));
+
+    assert_eq!(frame.dimensions(), (WIDTH, HEIGHT));
+    assert_pixel(&frame, 24, 24, [255, 0, 0, 255]);
+    assert_pixel(&frame, 4, 4, [0, 0, 0, 255]);
+    assert_pixel(&frame, 40, 40, [0, 0, 0, 255]);
+}
+
+#[test]
+fn overlapping_rectangles_match_the_golden_image()
+{
+    let mut draw = match headless_draw()
+    {
+        Some(draw) => draw,
+        None => return,
+    };
+
+    let frame = render(&mut draw, |draw|
+    {
+        draw.fill_rectangle(&Rectangle::new(Vector2::new(8.0, 8.0), 24.0, 16.0), Colour::RED);
+        draw.fill_rectangle(&Rectangle::new(Vector2::new(32.0, 24.0), 24.0, 24.0), Colour::GREEN);
+        draw.fill_rectangle(&Rectangle::new(Vector2::new(24.0, 32.0), 16.0, 16.0), Colour::BLUE);
+    });
+
+    assert_matches_golden_image(&frame, "overlapping_rectangles");
+}
+
+#[test]
+fn degenerate_shapes_draw_nothing()
+{
+    let mut draw = match headless_draw()
+    {
+        Some(draw) => draw,
+        None => return,
+    };
+
+    let point = Vector2::new(8.0, 8.0);
+
+    let frame = render(&mut draw, |draw|
+    {
+        draw.line(point, point, 2.0, Colour::RED);
+        draw.polyline(&[point], 2.0, Colour::RED);
+        draw.polygon(&[point, Vector2::new(20.0, 8.0)], Colour::RED);
+    });
+
+    assert_pixel(&frame, 8, 8, [0, 0, 0, 255]);
+}
+
+#[test]
+fn font_glyph_samples_its_own_atlas_area()
+{
+    let font_path = match system_font()
+    {
+        Some(font_path) => font_path,
+        None => return,
+    };
+
+    let mut draw = match headless_draw()
+    {
+        Some(draw) => draw,
+        None => return,
+    };
+
+    let size = 40.0;
+    let position = Vector2::new(8.0, 4.0);
+    let mut font = Font::new_from_file(&draw.graphics_interface, font_path);
+
+    // The stem of an `I` fills the middle of its bitmap, so the centre of the quad has to be lit.
+    let glyph = font.glyph(&draw.graphics_interface, 'I', size);
+    let left = (position.x + glyph.offset.x).round();
+    let top = (position.y + font.ascent(size) + glyph.offset.y).round();
+    let centre = ((left + glyph.width as f32 / 2.0) as u32, (top + glyph.height as f32 / 2.0) as u32);
+
+    let frame = render(&mut draw, |draw| draw.text(&mut font, "I", position, size, Colour::WHITE));
+
+    assert_pixel(&frame, centre.0, centre.1, [255, 255, 255, 255]);
+    assert_pixel(&frame, WIDTH - 2, HEIGHT - 2, [0, 0, 0, 255]);
+}
+
+#[test]
+fn full_font_atlas_is_repacked()
+{
+    let font_path = match system_font()
+    {
+        Some(font_path) => font_path,
+        None => return,
+    };
+
+    let mut draw = match headless_draw()
+    {
+        Some(draw) => draw,
+        None => return,
+    };
+
+    let mut font = Font::new_from_file(&draw.graphics_interface, font_path);
+
+    // Big enough that a few hundred of them can't share the largest atlas.
+    for size in 300..600 {
+        font.glyph(&draw.graphics_interface, 'W', size as f32);
+    }
+
+    let size = 40.0;
+    let position = Vector2::new(8.0, 4.0);
+    let glyph = font.glyph(&draw.graphics_interface, 'I', size);
+    let left = (position.x + glyph.offset.x).round();
+    let top = (position.y + font.ascent(size) + glyph.offset.y).round();
+    let centre = ((left + glyph.width as f32 / 2.0) as u32, (top + glyph.height as f32 / 2.0) as u32);
+
+    let frame = render(&mut draw, |draw| draw.text(&mut font, "I", position, size, Colour::WHITE));
+
+    assert_pixel(&frame, centre.0, centre.1, [255, 255, 255, 255]);
+}
+
+#[test]
+fn nearby_font_sizes_share_a_glyph()
+{
+    let font_path = match system_font()
+    {
+        Some(font_path) => font_path,
+        None => return,
+    };
+
+    let draw = match headless_draw()
+    {
+        Some(draw) => draw,
+        None => return,
+    };
+
+    let mut font = Font::new_from_file(&draw.graphics_interface, font_path);
+
+    let glyph = font.glyph(&draw.graphics_interface, 'A', 20.0);
+    let nearby_glyph = font.glyph(&draw.graphics_interface, 'A', 20.01);
+    let bigger_glyph = font.glyph(&draw.graphics_interface, 'A', 21.0);
+
+    assert_eq!(glyph.atlas_position, nearby_glyph.atlas_position);
+    assert_ne!(glyph.atlas_position, bigger_glyph.atlas_position);
+}
+
+#[test]
+fn bitmap_glyph_samples_its_own_page_area()
+{
+    let mut draw = match headless_draw()
+    {
+        Some(draw) => draw,
+        None => return,
+    };
+
+    let font_path = write_bitmap_font("bitmap_glyph", 0);
+
+    let font = match BitmapFont::new_from_file(&draw.graphics_interface, font_path.to_str().unwrap_or_default())
+    {
+        Ok(font) => font,
+        Err(e) => panic!("Couldn't load the bitmap font: {}", e),
+    };
+
+    let frame = render(&mut draw, |draw| draw.bitmap_text(&font, "A", Vector2::new(8.0, 8.0), 2.0, Colour::WHITE));
+
+    assert_pixel(&frame, 16, 16, [255, 255, 255, 255]);
+    assert_pixel(&frame, 30, 30, [0, 0, 0, 255]);
+}
+
+#[test]
+fn bitmap_font_with_missing_page_is_an_error()
+{
+    let draw = match headless_draw()
+    {
+        Some(draw) => draw,
+        None => return,
+    };
+
+    let font_path = write_bitmap_font("bitmap_missing_page", 3);
+
+    assert!(BitmapFont::new_from_file(&draw.graphics_interface, font_path.to_str().unwrap_or_default()).is_err());
+}
+
+#[test]
+fn bitmap_font_with_missing_page_image_is_an_error()
+{
+    let draw = match headless_draw()
+    {
+        Some(draw) => draw,
+        None => return,
+    };
+
+    let font_path = write_bitmap_font("bitmap_missing_page_image", 0);
+
+    if let Err(e) = fs::remove_file(font_path.with_file_name("page.png")) {
+        panic!("Couldn't remove the bitmap font page: {}", e);
+    }
+
+    assert!(BitmapFont::new_from_file(&draw.graphics_interface, font_path.to_str().unwrap_or_default()).is_err());
+}
+
+#[test]
+fn text_box_glyph_samples_its_own_page_area()
+{
+    let mut draw = match headless_draw()
+    {
+        Some(draw) => draw,
+        None => return,
+    };
+
+    let font_path = write_bitmap_font("text_box_glyph", 0);
+
+    let mut font = match BitmapFont::new_from_file(&draw.graphics_interface, font_path.to_str().unwrap_or_default())
+    {
+        Ok(font) => font,
+        Err(e) => panic!("Couldn't load the bitmap font: {}", e),
+    };
+
+    let bounds = Rectangle::new(Vector2::new(8.0, 8.0), 48.0, 48.0);
+    let frame = render(&mut draw, |draw| draw.text_box(&mut font, "[colour=red]A[/colour]", &bounds, &TextSettings::new(2.0), Colour::WHITE));
+
+    assert_pixel(&frame, 16, 16, [255, 0, 0, 255]);
+    assert_pixel(&frame, 30, 30, [0, 0, 0, 255]);
+}
+
+#[test]
+fn empty_viewport_draws_nothing()
+{
+    let mut draw = match headless_draw()
+    {
+        Some(draw) => draw,
+        None => return,
+    };
+
+    let frame = render(&mut draw, |draw|
+    {
+        draw.end();
+        draw.begin_with(Matrix4::identity(), BatchSettings::new().viewport(Rectangle::new(Vector2::new(0.0, 0.0), 0.0, 32.0)));
+        draw.fill_rectangle(&Rectangle::new(Vector2::new(0.0, 0.0), 32.0, 32.0), Colour::RED);
+    });
+
+    assert_pixel(&frame, 8, 8, [0, 0, 0, 255]);
+}
+
+#[test]
+fn camera_draws_into_its_own_viewport()
+{
+    let mut draw = match headless_draw()
+    {
+        Some(draw) => draw,
+        None => return,
+    };
+
+    let camera = Camera2D::with_viewport(Rectangle::new(Vector2::new((WIDTH / 2) as f32, 0.0), (WIDTH / 2) as f32, HEIGHT as f32));
+
+    let frame = render(&mut draw, |draw|
+    {
+        draw.end();
+        draw.begin(&camera);
+        draw.fill_rectangle(&Rectangle::new(Vector2::new(-100.0, -100.0), 200.0, 200.0), Colour::RED);
+    });
+
+    assert_pixel(&frame, WIDTH / 4, HEIGHT / 2, [0, 0, 0, 255]);
+    assert_pixel(&frame, WIDTH * 3 / 4, HEIGHT / 2, [255, 0, 0, 255]);
+}
+
+#[test]
+fn render_target_draws_as_a_sprite_but_not_into_itself()
+{
+    let mut draw = match headless_draw()
+    {
+        Some(draw) => draw,
+        None => return,
+    };
+
+    let render_target = RenderTarget::new(&draw.graphics_interface, 16, 16);
+    render_target.clear(&draw.graphics_interface, Colour::RED);
+
+    let area = Rectangle::new(Vector2::new(0.0, 0.0), 16.0, 16.0);
+    let size = Vector2::new(16.0, 16.0);
+
+    let frame = render(&mut draw, |draw|
+    {
+        draw.end();
+        draw.begin_with(Matrix4::identity(), BatchSettings::new().render_target(render_target.clone()));
+        draw.sprite(render_target.texture.clone(), Vector2::new(8.0, 8.0), &area, size, 0.0, Colour::WHITE);
+        draw.end();
+
+        draw.begin(Matrix4::identity());
+        draw.sprite(render_target.texture.clone(), Vector2::new(16.0, 16.0), &area, size, 0.0, Colour::WHITE);
+    });
+
+    assert_pixel(&frame, 16, 16, [255, 0, 0, 255]);
+    assert_pixel(&frame, 40, 40, [0, 0, 0, 255]);
+}
+
+#[test]
+fn integer_scaling_shrinks_to_fit_a_smaller_window()
+{
+    let mut draw = match headless_draw()
+    {
+        Some(draw) => draw,
+        None => return,
+    };
+
+    draw.set_virtual_resolution(WIDTH * 2, HEIGHT * 2, ScalingMode::Integer);
+
+    let frame = render(&mut draw, |draw| draw.fill_rectangle(&Rectangle::new(Vector2::new(0.0, 0.0), (WIDTH * 2) as f32, (HEIGHT * 2) as f32), Colour::RED));
+
+    assert_pixel(&frame, 1, 1, [255, 0, 0, 255]);
+    assert_pixel(&frame, WIDTH - 2, HEIGHT - 2, [255, 0, 0, 255]);
+}
+
+#[test]
+fn shapes_drawn_at_a_layer_depth_sort_back_to_front()
+{
+    let mut draw = match headless_draw()
+    {
+        Some(draw) => draw,
+        None => return,
+    };
+
+    let rectangle = Rectangle::new(Vector2::new(0.0, 0.0), 32.0, 32.0);
+
+    let frame = render(&mut draw, |draw|
+    {
+        draw.end();
+        draw.begin_with(Matrix4::identity(), BatchSettings::new().sort_mode(SortMode::BackToFront));
+        draw.with_layer_depth(0.0, |draw| draw.fill_rectangle(&rectangle, Colour::GREEN));
+        draw.with_layer_depth(1.0, |draw| draw.fill_rectangle(&rectangle, Colour::RED));
+    });
+
+    assert_pixel(&frame, 8, 8, [0, 255, 0, 255]);
+    assert_eq!(draw.layer_depth(), 0.0);
+}
+
+#[test]
+fn negative_scale_mirrors_the_sprite_instead_of_culling_it()
+{
+    let mut draw = match headless_draw()
+    {
+        Some(draw) => draw,
+        None => return,
+    };
+
+    let sheet = RgbaImage::from_fn(16, 16, |x, _| if x < 8 { image::Rgba([255, 0, 0, 255]) } else { image::Rgba([0, 0, 255, 255]) });
+    let texture = Arc::new(Texture::new_from_buffer(&draw.graphics_interface, sheet, Vector2::new(16, 16)));
+
+    let area = Rectangle::new(Vector2::new(0.0, 0.0), 16.0, 16.0);
+    let options = SpriteOptions::new().scale(Vector2::new(-1.0, 1.0));
+
+    let frame = render(&mut draw, |draw| draw.sprite_with(texture.clone(), Vector2::new(16.0, 16.0), &area, Vector2::new(16.0, 16.0), &options, Colour::WHITE));
+
+    assert_pixel(&frame, 12, 16, [0, 0, 255, 255]);
+    assert_pixel(&frame, 20, 16, [255, 0, 0, 255]);
+}